@@ -187,6 +187,7 @@ pub enum PoolError {
     #[msg("Hook program not whitelisted")]
     UnauthorizedHookProgram,
 
+    /// Deprecated: no longer returned, kept so the codes after it do not shift
     #[msg("Hook account resolution failed")]
     HookAccountResolutionFailed,
 
@@ -218,6 +219,7 @@ pub enum PoolError {
     #[msg("Pool state validation failed after hook execution")]
     PoolStateValidationFailed,
 
+    /// Deprecated: no longer returned, kept so the codes after it do not shift
     #[msg("Hook execution timeout - MEV protection")]
     HookExecutionTimeout,
 
@@ -226,4 +228,7 @@ pub enum PoolError {
 
     #[msg("Hook registry required for hook-enabled tokens")]
     MissingHookRegistry,
+
+    #[msg("Hook accounts do not describe the swap transfer")]
+    InvalidHookAccounts,
 }
@@ -0,0 +1,232 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    hash::hash,
+    instruction::{ AccountMeta, Instruction },
+    program::{ get_return_data, invoke },
+};
+use anchor_spl::token_interface::{ Mint, TokenAccount };
+
+use crate::{
+    activation_handler::ActivationHandler,
+    const_pda,
+    get_pool_access_validator,
+    params::swap::TradeDirection,
+    state::{ fee::FeeMode, HookRegistry, Pool },
+    token::{ calculate_transfer_fee_excluded_amount, has_transfer_hook },
+    PoolError,
+};
+
+/// Mirrors the `TransferVerdict` returned by the transfer hook's `check_transfer`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq)]
+pub struct HookTransferVerdict {
+    pub allowed: bool,
+    /// Hook error code the transfer would fail with, 0 when allowed
+    pub reason: u32,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct PreflightSwapParameters {
+    pub amount_in: u64,
+    pub has_referral: bool,
+    /// Number of remaining accounts for the input leg, the rest are for the output leg.
+    /// Each leg is `[hook_program, ..check_transfer accounts]`
+    pub input_hook_accounts_len: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct SwapPreflight {
+    pub allowed: bool,
+    pub output_amount: u64,
+    /// None when the input mint has no transfer hook
+    pub input_verdict: Option<HookTransferVerdict>,
+    /// None when the output mint has no transfer hook
+    pub output_verdict: Option<HookTransferVerdict>,
+}
+
+/// The transfer a leg's `check_transfer` accounts must describe. Indexes follow the hook's `CheckTransfer`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HookLeg {
+    pub mint: Pubkey,
+    pub source_owner: Pubkey,
+    pub source_token: Pubkey,
+    pub destination_owner: Pubkey,
+    pub destination_token: Pubkey,
+    /// Signer other than the source owner, checked against the hook's optional delegate account
+    pub delegate: Option<Pubkey>,
+}
+
+impl HookLeg {
+    const DELEGATE_INDEX: usize = 19;
+
+    /// `accounts` are the leg's `check_transfer` accounts, after the hook program. An absent optional
+    /// account is passed as the hook program id.
+    pub fn matches(&self, hook_program_id: &Pubkey, accounts: &[Pubkey]) -> bool {
+        let delegate = self.delegate.unwrap_or(*hook_program_id);
+        accounts.get(..5) ==
+            Some(&[self.mint, self.source_owner, self.source_token, self.destination_owner, self.destination_token]) &&
+            accounts.get(Self::DELEGATE_INDEX) == Some(&delegate)
+    }
+}
+
+#[derive(Accounts)]
+pub struct PreflightSwapCtx<'info> {
+    /// CHECK: pool authority, owner of the vaults
+    #[account(address = const_pda::pool_authority::ID)]
+    pub pool_authority: UncheckedAccount<'info>,
+
+    /// Pool account
+    #[account(has_one = token_a_vault, has_one = token_b_vault)]
+    pub pool: AccountLoader<'info, Pool>,
+
+    /// The user token account for input token
+    pub input_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The user token account for output token
+    pub output_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The vault token account for input token
+    #[account(token::mint = token_a_mint, token::authority = pool_authority)]
+    pub token_a_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The vault token account for output token
+    #[account(token::mint = token_b_mint, token::authority = pool_authority)]
+    pub token_b_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The mint of token a
+    pub token_a_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint of token b
+    pub token_b_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The user who would perform the swap
+    pub payer: Signer<'info>,
+
+    /// Optional hook registry for validating hook programs
+    pub hook_registry: Option<AccountLoader<'info, HookRegistry>>,
+}
+
+/// Quotes a swap and asks each leg's transfer hook whether it would pass, without moving tokens.
+/// Meant to be simulated, hook rejections come back as reason codes instead of an aborted CPI.
+pub fn handle_preflight_swap<'info>(
+    ctx: Context<'_, '_, 'info, 'info, PreflightSwapCtx<'info>>,
+    params: PreflightSwapParameters
+) -> Result<SwapPreflight> {
+    let pool = *ctx.accounts.pool.load()?;
+    {
+        let access_validator = get_pool_access_validator(&pool)?;
+        require!(access_validator.can_swap(&ctx.accounts.payer.key()), PoolError::PoolDisabled);
+    }
+
+    let PreflightSwapParameters { amount_in, has_referral, input_hook_accounts_len } = params;
+    let trade_direction = if ctx.accounts.input_token_account.mint == ctx.accounts.token_a_mint.key() {
+        TradeDirection::AtoB
+    } else {
+        TradeDirection::BtoA
+    };
+    let (token_in_mint, token_out_mint, input_vault, output_vault) = match trade_direction {
+        TradeDirection::AtoB =>
+            (
+                &ctx.accounts.token_a_mint,
+                &ctx.accounts.token_b_mint,
+                &ctx.accounts.token_a_vault,
+                &ctx.accounts.token_b_vault,
+            ),
+        TradeDirection::BtoA =>
+            (
+                &ctx.accounts.token_b_mint,
+                &ctx.accounts.token_a_mint,
+                &ctx.accounts.token_b_vault,
+                &ctx.accounts.token_a_vault,
+            ),
+    };
+    let input_token_account = &ctx.accounts.input_token_account;
+    let output_token_account = &ctx.accounts.output_token_account;
+    require!(
+        input_token_account.mint == token_in_mint.key() && output_token_account.mint == token_out_mint.key(),
+        PoolError::InvalidInput
+    );
+
+    let transfer_fee_excluded_amount_in = calculate_transfer_fee_excluded_amount(token_in_mint, amount_in)?.amount;
+    require!(transfer_fee_excluded_amount_in > 0, PoolError::AmountIsZero);
+
+    // quote against a copy, the pool itself is left untouched
+    let mut pool = pool;
+    let current_timestamp = Clock::get()?.unix_timestamp as u64;
+    pool.update_pre_swap(current_timestamp)?;
+    let current_point = ActivationHandler::get_current_point(pool.activation_type)?;
+    let fee_mode = &FeeMode::get_fee_mode(pool.collect_fee_mode, trade_direction, has_referral)?;
+    let swap_result = pool.get_swap_result(transfer_fee_excluded_amount_in, fee_mode, trade_direction, current_point)?;
+
+    let input_hook_program = has_transfer_hook(token_in_mint)?;
+    let output_hook_program = has_transfer_hook(token_out_mint)?;
+    if input_hook_program.is_some() || output_hook_program.is_some() {
+        let registry_loader = ctx.accounts.hook_registry.as_ref().ok_or(PoolError::MissingHookRegistry)?;
+        let registry = registry_loader.load()?;
+        for pid in input_hook_program.iter().chain(output_hook_program.iter()) {
+            require!(registry.is_program_whitelisted(pid), PoolError::UnauthorizedHookProgram);
+        }
+    }
+
+    let split = input_hook_accounts_len as usize;
+    require!(split <= ctx.remaining_accounts.len(), PoolError::MissingHookAccounts);
+    let (input_hook_accounts, output_hook_accounts) = ctx.remaining_accounts.split_at(split);
+    // a leg without a hook takes no accounts, so a wrong split cannot shift one leg into the other
+    require!(input_hook_program.is_some() || input_hook_accounts.is_empty(), PoolError::InvalidHookAccounts);
+    require!(output_hook_program.is_some() || output_hook_accounts.is_empty(), PoolError::InvalidHookAccounts);
+
+    let payer = ctx.accounts.payer.key();
+    let input_leg = HookLeg {
+        mint: token_in_mint.key(),
+        source_owner: input_token_account.owner,
+        source_token: input_token_account.key(),
+        destination_owner: const_pda::pool_authority::ID,
+        destination_token: input_vault.key(),
+        delegate: (payer != input_token_account.owner).then_some(payer),
+    };
+    let output_leg = HookLeg {
+        mint: token_out_mint.key(),
+        source_owner: const_pda::pool_authority::ID,
+        source_token: output_vault.key(),
+        destination_owner: output_token_account.owner,
+        destination_token: output_token_account.key(),
+        delegate: None,
+    };
+    let input_verdict = input_hook_program
+        .map(|pid| check_transfer(&pid, amount_in, &input_leg, input_hook_accounts))
+        .transpose()?;
+    let output_verdict = output_hook_program
+        .map(|pid| check_transfer(&pid, swap_result.output_amount, &output_leg, output_hook_accounts))
+        .transpose()?;
+
+    let allowed = input_verdict.iter().chain(output_verdict.iter()).all(|v| v.allowed);
+    Ok(SwapPreflight { allowed, output_amount: swap_result.output_amount, input_verdict, output_verdict })
+}
+
+/// CPI into the hook's `check_transfer` for `leg` and decode its return data
+fn check_transfer<'info>(
+    hook_program_id: &Pubkey,
+    amount: u64,
+    leg: &HookLeg,
+    accounts: &[AccountInfo<'info>]
+) -> Result<HookTransferVerdict> {
+    let (program, check_accounts) = accounts.split_first().ok_or(PoolError::MissingHookAccounts)?;
+    require_keys_eq!(program.key(), *hook_program_id, PoolError::MissingHookAccounts);
+    let keys: Vec<Pubkey> = check_accounts.iter().map(|ai| ai.key()).collect();
+    require!(leg.matches(hook_program_id, &keys), PoolError::InvalidHookAccounts);
+
+    let mut data = hash(b"global:check_transfer").to_bytes()[..8].to_vec();
+    data.extend_from_slice(&amount.to_le_bytes());
+    let instruction = Instruction {
+        program_id: *hook_program_id,
+        accounts: check_accounts
+            .iter()
+            .map(|ai| AccountMeta::new_readonly(ai.key(), false))
+            .collect(),
+        data,
+    };
+    invoke(&instruction, accounts)?;
+
+    let (program_id, return_data) = get_return_data().ok_or(PoolError::HookExecutionFailed)?;
+    require_keys_eq!(program_id, *hook_program_id, PoolError::HookExecutionFailed);
+    HookTransferVerdict::try_from_slice(&return_data).map_err(|_| PoolError::HookExecutionFailed.into())
+}
@@ -2,6 +2,8 @@ pub mod admin;
 pub use admin::*;
 pub mod ix_swap;
 pub use ix_swap::*;
+pub mod ix_preflight_swap;
+pub use ix_preflight_swap::*;
 pub mod ix_add_liquidity;
 pub use ix_add_liquidity::*;
 pub mod ix_create_position;
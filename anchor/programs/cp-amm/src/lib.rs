@@ -182,6 +182,13 @@ pub mod cp_amm {
         instructions::handle_swap(ctx, params)
     }
 
+    pub fn preflight_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PreflightSwapCtx<'info>>,
+        params: PreflightSwapParameters
+    ) -> Result<SwapPreflight> {
+        instructions::handle_preflight_swap(ctx, params)
+    }
+
     pub fn claim_position_fee(ctx: Context<ClaimPositionFeeCtx>) -> Result<()> {
         instructions::handle_claim_position_fee(ctx)
     }
@@ -27,3 +27,6 @@ mod fee_scheduler_tests;
 
 #[cfg(test)]
 mod test_volatility_accumulate;
+
+#[cfg(test)]
+mod preflight_tests;
@@ -0,0 +1,54 @@
+use anchor_lang::prelude::Pubkey;
+
+use crate::{ const_pda, instructions::HookLeg };
+
+fn leg(delegate: Option<Pubkey>) -> HookLeg {
+    HookLeg {
+        mint: Pubkey::new_unique(),
+        source_owner: Pubkey::new_unique(),
+        source_token: Pubkey::new_unique(),
+        destination_owner: const_pda::pool_authority::ID,
+        destination_token: Pubkey::new_unique(),
+        delegate,
+    }
+}
+
+/// `check_transfer` accounts for `leg`, with placeholders for the resolved PDAs
+fn accounts(leg: &HookLeg, hook_program_id: &Pubkey) -> Vec<Pubkey> {
+    let mut keys = vec![leg.mint, leg.source_owner, leg.source_token, leg.destination_owner, leg.destination_token];
+    keys.extend((5..19).map(|_| Pubkey::new_unique()));
+    keys.push(leg.delegate.unwrap_or(*hook_program_id));
+    keys.push(Pubkey::new_unique());
+    keys
+}
+
+#[test]
+fn test_leg_accounts_must_describe_the_swap_transfer() {
+    let hook = Pubkey::new_unique();
+    let leg = leg(None);
+    let keys = accounts(&leg, &hook);
+    assert!(leg.matches(&hook, &keys));
+
+    // any of mint, source owner, source token, destination owner or destination token swapped out
+    for index in 0..5 {
+        let mut other = keys.clone();
+        other[index] = Pubkey::new_unique();
+        assert!(!leg.matches(&hook, &other));
+    }
+    assert!(!leg.matches(&hook, &keys[..4]));
+}
+
+#[test]
+fn test_delegate_must_be_the_swapper() {
+    let hook = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let leg = leg(Some(payer));
+    let mut keys = accounts(&leg, &hook);
+    assert!(leg.matches(&hook, &keys));
+
+    // checked as if the owner signed
+    keys[19] = hook;
+    assert!(!leg.matches(&hook, &keys));
+    keys[19] = Pubkey::new_unique();
+    assert!(!leg.matches(&hook, &keys));
+}
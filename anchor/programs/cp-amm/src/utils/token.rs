@@ -320,34 +320,19 @@ pub fn transfer_from_user_with_hooks<'info>(
 
         // Whitelist validation is done in instruction layer (swap), not here
 
-        // 🛡️ HOOK EXECUTION: Use hook-aware transfer with enhanced error handling
-        match
-            transfer_with_hook_support(
-                authority.to_account_info(),
-                token_mint,
-                token_owner_account,
-                destination_token_account,
-                token_program,
-                amount,
-                remaining_accounts
-            )
-        {
-            Ok(()) => {
-                msg!("✅ Hook-enabled transfer completed successfully");
-                Ok(())
-            }
-            Err(e) => {
-                msg!("❌ Hook execution failed: {:?}", e);
-                // Enhanced error handling for hook failures
-                if e.to_string().contains("insufficient compute units") {
-                    return Err(crate::PoolError::HookExecutionTimeout.into());
-                } else if e.to_string().contains("invalid account") {
-                    return Err(crate::PoolError::HookAccountResolutionFailed.into());
-                } else {
-                    return Err(crate::PoolError::HookExecutionFailed.into());
-                }
-            }
-        }
+        // a failing hook aborts the whole transaction, so there is no error to map here;
+        // clients call preflight_swap to learn why a hook would reject
+        transfer_with_hook_support(
+            authority.to_account_info(),
+            token_mint,
+            token_owner_account,
+            destination_token_account,
+            token_program,
+            amount,
+            remaining_accounts
+        )?;
+        msg!("✅ Hook-enabled transfer completed successfully");
+        Ok(())
     } else {
         // Use standard transfer (backward compatibility)
         transfer_from_user(authority, token_mint, token_owner_account, destination_token_account, token_program, amount)
@@ -370,34 +355,19 @@ pub fn transfer_from_pool_with_hooks<'info>(
 
         // Whitelist validation is done in instruction layer (swap), not here
 
-        // 🛡️ HOOK EXECUTION: Use hook-aware transfer with enhanced error handling
-        match
-            transfer_with_hook_support_signed(
-                pool_authority,
-                token_mint,
-                token_vault,
-                token_owner_account,
-                token_program,
-                amount,
-                remaining_accounts
-            )
-        {
-            Ok(()) => {
-                msg!("✅ Hook-enabled pool transfer completed successfully");
-                Ok(())
-            }
-            Err(e) => {
-                msg!("❌ Hook execution failed in pool transfer: {:?}", e);
-                // Enhanced error handling for hook failures
-                if e.to_string().contains("insufficient compute units") {
-                    return Err(crate::PoolError::HookExecutionTimeout.into());
-                } else if e.to_string().contains("invalid account") {
-                    return Err(crate::PoolError::HookAccountResolutionFailed.into());
-                } else {
-                    return Err(crate::PoolError::HookExecutionFailed.into());
-                }
-            }
-        }
+        // a failing hook aborts the whole transaction, so there is no error to map here;
+        // clients call preflight_swap to learn why a hook would reject
+        transfer_with_hook_support_signed(
+            pool_authority,
+            token_mint,
+            token_vault,
+            token_owner_account,
+            token_program,
+            amount,
+            remaining_accounts
+        )?;
+        msg!("✅ Hook-enabled pool transfer completed successfully");
+        Ok(())
     } else {
         // Use standard transfer (backward compatibility)
         transfer_from_pool(pool_authority, token_mint, token_vault, token_owner_account, token_program, amount)
@@ -454,7 +424,12 @@ fn transfer_with_hook_support<'info>(
     // Append all other remaining accounts (skip ones we already added)
     for ai in remaining_accounts.iter() {
         if !account_infos.iter().any(|x| x.key() == ai.key()) {
-            instruction.accounts.push(AccountMeta::new_readonly(ai.key(), false));
+            // hook extras such as holding lots are written by the hook
+            instruction.accounts.push(if ai.is_writable {
+                AccountMeta::new(ai.key(), false)
+            } else {
+                AccountMeta::new_readonly(ai.key(), false)
+            });
             account_infos.push(ai.clone());
         }
     }
@@ -525,7 +500,12 @@ fn transfer_with_hook_support_signed<'info>(
     }
     for ai in remaining_accounts.iter() {
         if !account_infos.iter().any(|x| x.key() == ai.key()) {
-            instruction.accounts.push(AccountMeta::new_readonly(ai.key(), false));
+            // hook extras such as holding lots are written by the hook
+            instruction.accounts.push(if ai.is_writable {
+                AccountMeta::new(ai.key(), false)
+            } else {
+                AccountMeta::new_readonly(ai.key(), false)
+            });
             account_infos.push(ai.clone());
         }
     }
@@ -11,4 +11,86 @@ pub enum CustomError {
     #[msg("Invalid country code")] InvalidCountryCode,
     #[msg("Invalid state code")] InvalidStateCode,
     #[msg("Invalid city name")] InvalidCityName,
+    #[msg("Signer is not the mint's transfer hook authority")] InvalidTransferHookAuthority,
+    #[msg("Signer is not the compliance authority")] Unauthorized,
+    #[msg("Invalid exemption")] InvalidExemption,
+    #[msg("Exemption registry full")] ExemptionRegistryFull,
+    #[msg("Exemption already exists")] ExemptionAlreadyExists,
+    #[msg("Exemption not found")] ExemptionNotFound,
+    #[msg("User KYC expired")] UserKycExpired,
+    #[msg("User KYC in grace period, only outgoing transfers allowed")] UserKycInGracePeriod,
+    #[msg("Signer is not a KYC officer")] NotKycOfficer,
+    #[msg("KYC officer list full")] OfficerListFull,
+    #[msg("KYC officer already exists")] OfficerAlreadyExists,
+    #[msg("KYC officer not found")] OfficerNotFound,
+    #[msg("Invalid validity period")] InvalidValidityPeriod,
+    #[msg("KYC provider registry full")] ProviderRegistryFull,
+    #[msg("KYC provider already exists")] ProviderAlreadyExists,
+    #[msg("KYC provider not found")] ProviderNotFound,
+    #[msg("Missing or malformed Ed25519 signature instruction")] InvalidEd25519Instruction,
+    #[msg("Attestation does not match signed message")] InvalidAttestation,
+    #[msg("Attestation is expired")] AttestationExpired,
+    #[msg("Attestation is older than current verification")] StaleAttestation,
+    #[msg("User KYC record must be migrated to the current layout")] UserKycMigrationRequired,
+    #[msg("Location data cannot be stored on a private KYC record")] PrivateKycRecord,
+    #[msg("Transfer amount exceeds unlocked balance")] TokensLocked,
+    #[msg("Holding lots account not initialized for holder")] HoldingLotsNotInitialized,
+    #[msg("Invalid investor class")] InvalidInvestorClass,
+    #[msg("Invalid lockup period")] InvalidLockupPeriod,
+    #[msg("Transfer would exceed the maximum number of holders")] HolderLimitExceeded,
+    #[msg("Transfer would exceed the maximum ownership per holder")] HolderOwnershipLimitExceeded,
+    #[msg("Invalid holder limit")] InvalidHolderLimit,
+    #[msg("Recovery must be approved by a different officer")] RecoveryApprovalRequired,
+    #[msg("Recovery already approved")] RecoveryAlreadyApproved,
+    #[msg("Mint permanent delegate is not the program recovery authority")] InvalidPermanentDelegate,
+    #[msg("Invalid recovery wallet")] InvalidRecoveryWallet,
+    #[msg("Holder is frozen for this mint")] HolderFrozenForMint,
+    #[msg("Holder is not whitelisted for this mint")] HolderNotWhitelisted,
+    #[msg("Invalid compliance module")] InvalidComplianceModule,
+    #[msg("Too many compliance modules")] TooManyComplianceModules,
+    #[msg("Holder registry not initialized for mint")] HolderRegistryNotInitialized,
+    #[msg("Investor class not allowed for this mint")] InvestorClassNotAllowed,
+    #[msg("Transfer volume limit exceeded")] VolumeLimitExceeded,
+    #[msg("Invalid investor flags")] InvalidInvestorFlags,
+    #[msg("Investor qualifications do not meet mint requirements")] InvestorRequirementsNotMet,
+    #[msg("Politically exposed person requires enhanced due diligence")] PepDueDiligenceRequired,
+    #[msg("Risk score must be between 0 and 100")] InvalidRiskScore,
+    #[msg("Risk band thresholds must be ascending")] InvalidRiskBands,
+    #[msg("Risk score blocks transfers")] RiskScoreBlocked,
+    #[msg("Amount exceeds the limit for this risk score")] RiskAmountLimitExceeded,
+    #[msg("Transfer requires compliance approval")] TransferApprovalRequired,
+    #[msg("Transfer exceeds the approved amount")] TransferApprovalExceeded,
+    #[msg("Invalid cross-border route")] InvalidCrossBorderRoute,
+    #[msg("Transfer between these jurisdictions is not allowed")] CrossBorderTransferNotAllowed,
+    #[msg("Invalid trading session")] InvalidTradingSession,
+    #[msg("Outside trading session")] OutsideTradingSession,
+    #[msg("Too many holidays")] TooManyHolidays,
+    #[msg("Travel rule threshold must be positive")] InvalidTravelRuleThreshold,
+    #[msg("Transfer requires a matching travel rule record")] TravelRuleRecordRequired,
+    #[msg("Travel rule nonce must increase")] InvalidTravelRuleNonce,
+    #[msg("Transfer journal full, archive a page first")] TransferJournalFull,
+    #[msg("Transfer journal page not full")] TransferJournalPageNotFull,
+    #[msg("Invalid velocity limits")] InvalidVelocityLimits,
+    #[msg("Transfer velocity limit exceeded")] VelocityLimitExceeded,
+    #[msg("Change requires approval by a second officer")] DualControlRequired,
+    #[msg("Pending change must be approved by a different officer")] SameOfficerApproval,
+    #[msg("Pending change expired")] PendingChangeExpired,
+    #[msg("Pending change is empty")] EmptyPendingChange,
+    #[msg("Too many batch entries")] TooManyBatchEntries,
+    #[msg("Batch account does not match entry")] InvalidBatchAccount,
+    #[msg("User KYC already exists")] UserKycAlreadyExists,
+    #[msg("Not enough compute left for batch entry")] BatchComputeExhausted,
+    #[msg("User still holds policy-governed tokens")] UserHoldsPolicyTokens,
+    #[msg("Wallet is not linked to an identity")] WalletNotLinked,
+    #[msg("Mint has no token metadata")] MintMetadataNotFound,
+    #[msg("Transfer hook must be invoked by Token-2022 during a transfer")] NotTransferring,
+    #[msg("Holding lots are full until the oldest lot unlocks")] HoldingLotsFull,
+    #[msg("Holder position not initialized for holder")] HolderPositionNotInitialized,
+    #[msg("Recovery request has expired")] RecoveryExpired,
+    #[msg("Invalid holder status flags")] InvalidHolderStatusFlags,
+    #[msg("Approval expiry must be in the future and within the maximum lifetime")] InvalidApprovalExpiry,
+    #[msg("Travel rule record is still pending")] TravelRuleRecordPending,
+    #[msg("KYC record carries a restriction only dual control can lift")] KycRecordRestricted,
+    #[msg("Wallets are still linked to this identity")] IdentityHasLinkedWallets,
+    #[msg("Mint resolves identities, pass the identity accounts")] IdentityAccountsRequired,
 }
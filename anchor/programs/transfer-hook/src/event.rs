@@ -0,0 +1,310 @@
+use anchor_lang::prelude::*;
+use spl_tlv_account_resolution::account::ExtraAccountMeta;
+
+use crate::state::{ ComplianceMetadata, ComplianceModule };
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct ExtraAccountMetaRecord {
+    pub discriminator: u8,
+    pub address_config: [u8; 32],
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl From<&ExtraAccountMeta> for ExtraAccountMetaRecord {
+    fn from(meta: &ExtraAccountMeta) -> Self {
+        Self {
+            discriminator: meta.discriminator,
+            address_config: meta.address_config,
+            is_signer: meta.is_signer.into(),
+            is_writable: meta.is_writable.into(),
+        }
+    }
+}
+
+#[event]
+pub struct EvtUpdateExtraAccountMetaList {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub version: u8,
+    pub old_metas: Vec<ExtraAccountMetaRecord>,
+    pub new_metas: Vec<ExtraAccountMetaRecord>,
+}
+
+#[event]
+pub struct EvtAddExemption {
+    pub authority: Pubkey,
+    pub key: Pubkey,
+    pub kind: u8,
+}
+
+#[event]
+pub struct EvtRemoveExemption {
+    pub authority: Pubkey,
+    pub key: Pubkey,
+}
+
+#[event]
+pub struct EvtRenewKyc {
+    pub officer: Pubkey,
+    pub user: Pubkey,
+    pub kyc_level: u8,
+    pub verified_at: i64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct EvtKycAttested {
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub kyc_level: u8,
+    pub issued_at: i64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct EvtMigrateUserKyc {
+    pub user: Pubkey,
+    pub old_len: u64,
+    pub version: u8,
+}
+
+#[event]
+pub struct EvtPrivatizeUserKyc {
+    pub officer: Pubkey,
+    pub user: Pubkey,
+    pub identity_hash: [u8; 32],
+    pub country_group_mask: u32,
+}
+
+#[event]
+pub struct EvtSetInvestorClass {
+    pub officer: Pubkey,
+    pub user: Pubkey,
+    pub investor_class: u8,
+}
+
+#[event]
+pub struct EvtSetInvestorFlags {
+    pub officer: Pubkey,
+    pub user: Pubkey,
+    pub investor_flags: u8,
+}
+
+#[event]
+pub struct EvtProposeRecovery {
+    pub mint: Pubkey,
+    pub old_owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub officer: Pubkey,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct EvtApproveRecovery {
+    pub mint: Pubkey,
+    pub old_owner: Pubkey,
+    pub officer: Pubkey,
+}
+
+#[event]
+pub struct EvtCancelRecovery {
+    pub mint: Pubkey,
+    pub old_owner: Pubkey,
+    pub officer: Pubkey,
+}
+
+#[event]
+pub struct EvtExecuteRecovery {
+    pub mint: Pubkey,
+    pub old_owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub old_token: Pubkey,
+    pub new_token: Pubkey,
+    pub amount: u64,
+    pub proposed_by: Pubkey,
+    pub approved_by: Pubkey,
+    pub hook_authority: Pubkey,
+}
+
+#[event]
+pub struct EvtSetHolderStatus {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub holder: Pubkey,
+    pub flags: u8,
+    pub investor_class: Option<u8>,
+}
+
+#[event]
+pub struct EvtSetLockupPeriods {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub lockup_periods: [i64; 4],
+}
+
+#[event]
+pub struct EvtSetWhitelistRequired {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub whitelist_required: bool,
+}
+
+#[event]
+pub struct EvtSetHolderLimits {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub max_holders: u32,
+    pub max_holder_bps: u16,
+}
+
+#[event]
+pub struct EvtSetComplianceModules {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub modules: Vec<ComplianceModule>,
+}
+
+#[event]
+pub struct EvtApproveTransfer {
+    pub mint: Pubkey,
+    pub source_owner: Pubkey,
+    pub destination_owner: Pubkey,
+    pub officer: Pubkey,
+    pub amount: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct EvtRevokeTransferApproval {
+    pub mint: Pubkey,
+    pub source_owner: Pubkey,
+    pub destination_owner: Pubkey,
+    pub officer: Pubkey,
+}
+
+#[event]
+pub struct EvtSetHolidayCalendar {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub days: Vec<i64>,
+}
+
+#[event]
+pub struct EvtRecordTravelRule {
+    pub mint: Pubkey,
+    pub source_owner: Pubkey,
+    pub destination_owner: Pubkey,
+    pub nonce: u64,
+    pub amount: u64,
+    pub originator_hash: [u8; 32],
+    pub beneficiary_hash: [u8; 32],
+    pub officer: Pubkey,
+}
+
+#[event]
+pub struct EvtConsumeTravelRule {
+    pub mint: Pubkey,
+    pub source_owner: Pubkey,
+    pub destination_owner: Pubkey,
+    pub nonce: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct EvtCancelTravelRule {
+    pub mint: Pubkey,
+    pub source_owner: Pubkey,
+    pub destination_owner: Pubkey,
+    pub nonce: u64,
+    pub officer: Pubkey,
+}
+
+#[event]
+pub struct EvtArchiveJournalPage {
+    pub mint: Pubkey,
+    pub page: u64,
+    pub archive: Pubkey,
+}
+
+#[event]
+pub struct EvtVelocityReview {
+    pub mint: Pubkey,
+    pub user: Pubkey,
+    pub daily_transfer_count: u32,
+    pub daily_near_threshold_count: u32,
+    pub monthly_near_threshold_count: u32,
+    pub risk_score: u8,
+}
+
+#[event]
+pub struct EvtProposeKycChange {
+    pub user: Pubkey,
+    pub officer: Pubkey,
+    pub kyc_level: Option<u8>,
+    pub flags_to_set: u8,
+    pub flags_to_clear: u8,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct EvtApproveKycChange {
+    pub user: Pubkey,
+    pub proposed_by: Pubkey,
+    pub approved_by: Pubkey,
+    pub kyc_level: u8,
+    pub flags: u8,
+}
+
+#[event]
+pub struct EvtCancelKycChange {
+    pub user: Pubkey,
+    pub proposed_by: Pubkey,
+    pub officer: Pubkey,
+}
+
+#[event]
+pub struct EvtSetDualControl {
+    pub authority: Pubkey,
+    pub dual_control_level: u8,
+    pub pending_change_ttl: i64,
+}
+
+#[event]
+pub struct EvtCloseUserKyc {
+    pub officer: Pubkey,
+    pub user: Pubkey,
+    /// Hash of the final account data, for matching against off-chain archives
+    pub record_hash: [u8; 32],
+    pub refund_to: Pubkey,
+}
+
+#[event]
+pub struct EvtLinkWallet {
+    pub officer: Pubkey,
+    pub wallet: Pubkey,
+    pub identity: Pubkey,
+}
+
+#[event]
+pub struct EvtUnlinkWallet {
+    pub officer: Pubkey,
+    pub wallet: Pubkey,
+    pub identity: Pubkey,
+}
+
+#[event]
+pub struct EvtSetComplianceMetadata {
+    pub mint: Pubkey,
+    pub update_authority: Pubkey,
+    pub field: ComplianceMetadata,
+}
+
+#[event]
+pub struct EvtSyncHolderPosition {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub holder: Pubkey,
+    pub old_balance: u64,
+    pub new_balance: u64,
+}
@@ -1,59 +1,989 @@
 use anchor_lang::prelude::*;
-use anchor_spl::{ associated_token::AssociatedToken, token_interface::{ Mint, TokenAccount, TokenInterface } };
+use anchor_lang::solana_program::{
+    compute_units::sol_remaining_compute_units,
+    ed25519_program,
+    hash::hash,
+    program::invoke,
+    sysvar::instructions::{ self as instructions_sysvar, load_current_index_checked, load_instruction_at_checked },
+};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_2022::spl_token_2022::{
+        extension::{
+            permanent_delegate::PermanentDelegate,
+            transfer_hook::{
+                instruction as transfer_hook_instruction,
+                TransferHook as TransferHookExtension,
+                TransferHookAccount,
+            },
+            BaseStateWithExtensions,
+            StateWithExtensions,
+        },
+        state::{ Account as TokenAccountState, Mint as MintState },
+    },
+    token_interface::{
+        self,
+        Mint,
+        Token2022,
+        TokenAccount,
+        TokenInterface,
+        TokenMetadataUpdateField,
+        TransferChecked,
+    },
+};
+use spl_token_metadata_interface::{ instruction::remove_key, state::Field };
 use spl_transfer_hook_interface::instruction::ExecuteInstruction;
-use spl_tlv_account_resolution::{ account::ExtraAccountMeta, seeds::Seed, state::ExtraAccountMetaList };
+use spl_tlv_account_resolution::{
+    account::ExtraAccountMeta,
+    pubkey_data::PubkeyData,
+    seeds::Seed,
+    state::ExtraAccountMetaList,
+};
+use spl_type_length_value::state::TlvStateBorrowed;
+
+use crate::{
+    error::CustomError,
+    event::{
+        EvtAddExemption,
+        EvtApproveKycChange,
+        EvtApproveRecovery,
+        EvtApproveTransfer,
+        EvtArchiveJournalPage,
+        EvtCancelKycChange,
+        EvtCancelRecovery,
+        EvtCancelTravelRule,
+        EvtCloseUserKyc,
+        EvtConsumeTravelRule,
+        EvtExecuteRecovery,
+        EvtKycAttested,
+        EvtLinkWallet,
+        EvtMigrateUserKyc,
+        EvtPrivatizeUserKyc,
+        EvtProposeKycChange,
+        EvtProposeRecovery,
+        EvtRecordTravelRule,
+        EvtRemoveExemption,
+        EvtRenewKyc,
+        EvtRevokeTransferApproval,
+        EvtSetComplianceMetadata,
+        EvtSetComplianceModules,
+        EvtSetDualControl,
+        EvtSetHolderLimits,
+        EvtSetHolderStatus,
+        EvtSetHolidayCalendar,
+        EvtSetInvestorClass,
+        EvtSetInvestorFlags,
+        EvtSetLockupPeriods,
+        EvtSetWhitelistRequired,
+        EvtSyncHolderPosition,
+        EvtUnlinkWallet,
+        EvtUpdateExtraAccountMetaList,
+        EvtVelocityReview,
+        ExtraAccountMetaRecord,
+    },
+    state::{
+        ComplianceConfig,
+        ComplianceMetadata,
+        ComplianceModule,
+        BatchKycEntry,
+        BatchKycResult,
+        BatchKycUpdate,
+        CountryGroups,
+        ExemptionRegistry,
+        HolderPosition,
+        HolderRegistry,
+        HolderStatus,
+        HoldingLots,
+        HolidayCalendar,
+        IdentityLinks,
+        JournalArchive,
+        JournalEntry,
+        KycAttestation,
+        KycProvider,
+        KycProviderRegistry,
+        MintPolicy,
+        PendingKycChange,
+        RecoveryRequest,
+        RwaMetadata,
+        SessionWindow,
+        TransferApproval,
+        TransferJournal,
+        TransferVerdict,
+        TravelRuleRecord,
+        UserKYC,
+        UserKycUpdate,
+        Token2022MetadataParser,
+        WalletLink,
+    },
+};
+
+/// Bumped whenever `extra_account_metas` changes, so clients know to refresh their resolution.
+pub const EXTRA_ACCOUNT_METAS_VERSION: u8 = 16;
+
+/// Owner of the source token account. [3] is whoever signed, the owner or an approved delegate.
+const SOURCE_OWNER_SEED: Seed = Seed::AccountData { account_index: 0, data_index: 32, length: 32 };
 
-use crate::{ error::CustomError, state::{ UserKYC, Token2022MetadataParser } };
+/// Resolved after the standard accounts: [0] source_token, [1] mint, [2] destination_token, [3] owner or
+/// delegate, [4] extra_account_meta_list
+pub fn extra_account_metas() -> Result<Vec<ExtraAccountMeta>> {
+    Ok(
+        vec![
+            // [5] user_kyc of the source owner, writable for the velocity review flag and open position count
+            ExtraAccountMeta::new_with_seeds(
+                &[Seed::Literal { bytes: b"user-kyc".to_vec() }, SOURCE_OWNER_SEED],
+                false,
+                true
+            )?,
+            // [6] exemption_registry
+            ExtraAccountMeta::new_with_seeds(&[Seed::Literal { bytes: b"exemption-registry".to_vec() }], false, false)?,
+            // [7] destination_owner
+            ExtraAccountMeta::new_with_pubkey_data(
+                &(PubkeyData::AccountData { account_index: 2, data_index: 32 }),
+                false,
+                false
+            )?,
+            // [8] destination_kyc, writable for its open position count
+            ExtraAccountMeta::new_with_seeds(
+                &[Seed::Literal { bytes: b"user-kyc".to_vec() }, Seed::AccountKey { index: 7 }],
+                false,
+                true
+            )?,
+            // [9] compliance_config
+            ExtraAccountMeta::new_with_seeds(&[Seed::Literal { bytes: b"compliance-config".to_vec() }], false, false)?,
+            // [10] mint_policy
+            ExtraAccountMeta::new_with_seeds(
+                &[Seed::Literal { bytes: b"mint-policy".to_vec() }, Seed::AccountKey { index: 1 }],
+                false,
+                false
+            )?,
+            // [11] source_lots
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal { bytes: b"holding-lots".to_vec() },
+                    Seed::AccountKey { index: 1 },
+                    SOURCE_OWNER_SEED,
+                ],
+                false,
+                true
+            )?,
+            // [12] destination_lots
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal { bytes: b"holding-lots".to_vec() },
+                    Seed::AccountKey { index: 1 },
+                    Seed::AccountKey { index: 7 },
+                ],
+                false,
+                true
+            )?,
+            // [13] holder_registry
+            ExtraAccountMeta::new_with_seeds(
+                &[Seed::Literal { bytes: b"holder-registry".to_vec() }, Seed::AccountKey { index: 1 }],
+                false,
+                true
+            )?,
+            // [14] source_status
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal { bytes: b"holder-status".to_vec() },
+                    Seed::AccountKey { index: 1 },
+                    SOURCE_OWNER_SEED,
+                ],
+                false,
+                false
+            )?,
+            // [15] destination_status
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal { bytes: b"holder-status".to_vec() },
+                    Seed::AccountKey { index: 1 },
+                    Seed::AccountKey { index: 7 },
+                ],
+                false,
+                false
+            )?,
+            // [16] transfer_approval, writable to draw down the approved amount
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal { bytes: b"transfer-approval".to_vec() },
+                    Seed::AccountKey { index: 1 },
+                    SOURCE_OWNER_SEED,
+                    Seed::AccountKey { index: 7 },
+                ],
+                false,
+                true
+            )?,
+            // [17] holiday_calendar
+            ExtraAccountMeta::new_with_seeds(
+                &[Seed::Literal { bytes: b"holiday-calendar".to_vec() }, Seed::AccountKey { index: 1 }],
+                false,
+                false
+            )?,
+            // [18] travel_rule_record, writable to mark it consumed
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal { bytes: b"travel-rule".to_vec() },
+                    Seed::AccountKey { index: 1 },
+                    SOURCE_OWNER_SEED,
+                    Seed::AccountKey { index: 7 },
+                ],
+                false,
+                true
+            )?,
+            // [19] transfer_journal
+            ExtraAccountMeta::new_with_seeds(
+                &[Seed::Literal { bytes: b"transfer-journal".to_vec() }, Seed::AccountKey { index: 1 }],
+                false,
+                true
+            )?,
+            // [20] source_owner
+            ExtraAccountMeta::new_with_pubkey_data(
+                &(PubkeyData::AccountData { account_index: 0, data_index: 32 }),
+                false,
+                false
+            )?,
+            // [21] delegate_kyc, the source owner's own record when the owner signed
+            ExtraAccountMeta::new_with_seeds(
+                &[Seed::Literal { bytes: b"user-kyc".to_vec() }, Seed::AccountKey { index: 3 }],
+                false,
+                false
+            )?,
+            // [22] source_position
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal { bytes: b"holder-position".to_vec() },
+                    Seed::AccountKey { index: 1 },
+                    SOURCE_OWNER_SEED,
+                ],
+                false,
+                true
+            )?,
+            // [23] destination_position
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal { bytes: b"holder-position".to_vec() },
+                    Seed::AccountKey { index: 1 },
+                    Seed::AccountKey { index: 7 },
+                ],
+                false,
+                true
+            )?
+        ]
+    )
+}
+
+/// Appended to `extra_account_metas` on mints that resolve identities. Resolution reads the wallet links,
+/// so every holder of such a mint needs one, exempt wallets included (linked to themselves). Positions
+/// are the identities', so caps and counters apply across all their wallets.
+pub fn identity_account_metas() -> Result<Vec<ExtraAccountMeta>> {
+    let linked_identity = |link_index: u8| Seed::AccountData {
+        account_index: link_index,
+        data_index: WalletLink::IDENTITY_OFFSET,
+        length: 32,
+    };
+    let identity_seeds = |link_index: u8| [Seed::Literal { bytes: b"user-kyc".to_vec() }, linked_identity(link_index)];
+    let position_seeds = |link_index: u8| [
+        Seed::Literal { bytes: b"holder-position".to_vec() },
+        Seed::AccountKey { index: 1 },
+        linked_identity(link_index),
+    ];
+    Ok(
+        vec![
+            // [24] source_wallet_link
+            ExtraAccountMeta::new_with_seeds(
+                &[Seed::Literal { bytes: b"wallet-link".to_vec() }, Seed::AccountKey { index: 20 }],
+                false,
+                false
+            )?,
+            // [25] destination_wallet_link
+            ExtraAccountMeta::new_with_seeds(
+                &[Seed::Literal { bytes: b"wallet-link".to_vec() }, Seed::AccountKey { index: 7 }],
+                false,
+                false
+            )?,
+            // [26] source_identity, writable for the velocity review flag and open position count
+            ExtraAccountMeta::new_with_seeds(&identity_seeds(24), false, true)?,
+            // [27] destination_identity, writable for its open position count
+            ExtraAccountMeta::new_with_seeds(&identity_seeds(25), false, true)?,
+            // [28] source_identity_position
+            ExtraAccountMeta::new_with_seeds(&position_seeds(24), false, true)?,
+            // [29] destination_identity_position
+            ExtraAccountMeta::new_with_seeds(&position_seeds(25), false, true)?
+        ]
+    )
+}
 
 #[derive(Accounts)]
 pub struct TransferHook<'info> {
-    #[account(token::mint = mint, token::authority = owner)]
+    #[account(token::mint = mint)]
     pub source_token: InterfaceAccount<'info, TokenAccount>,
     pub mint: InterfaceAccount<'info, Mint>,
     #[account(token::mint = mint)]
     pub destination_token: InterfaceAccount<'info, TokenAccount>,
-    /// CHECK: owner can be system account or PDA
-    pub owner: UncheckedAccount<'info>,
+    /// CHECK: source owner or an approved delegate, whoever signed the transfer
+    pub authority: UncheckedAccount<'info>,
     /// CHECK: ExtraAccountMetaList PDA
     #[account(seeds = [b"extra-account-metas", mint.key().as_ref()], bump)]
     pub extra_account_meta_list: UncheckedAccount<'info>,
-    /// PDA user KYC, must belong to owner
-    #[account(seeds = [b"user-kyc", owner.key().as_ref()], bump, constraint = user_kyc.user == owner.key() @ CustomError::UserKycNotFound)]
-    pub user_kyc: Account<'info, UserKYC>,
+    /// CHECK: PDA user KYC of source owner, may be absent when the source owner is exempt
+    #[account(mut, seeds = [b"user-kyc", source_token.owner.as_ref()], bump)]
+    pub user_kyc: UncheckedAccount<'info>,
+    /// CHECK: exemption registry PDA, treated as empty until initialized
+    #[account(seeds = [b"exemption-registry"], bump)]
+    pub exemption_registry: UncheckedAccount<'info>,
+    /// CHECK: owner of destination_token
+    #[account(address = destination_token.owner)]
+    pub destination_owner: UncheckedAccount<'info>,
+    /// CHECK: PDA user KYC of destination owner, may be absent when destination owner is exempt
+    #[account(mut, seeds = [b"user-kyc", destination_owner.key().as_ref()], bump)]
+    pub destination_kyc: UncheckedAccount<'info>,
+    /// CHECK: compliance config PDA, defaults apply until initialized
+    #[account(seeds = [b"compliance-config"], bump)]
+    pub compliance_config: UncheckedAccount<'info>,
+    /// CHECK: mint policy PDA, the default compliance chain applies until initialized
+    #[account(seeds = [b"mint-policy", mint.key().as_ref()], bump)]
+    pub mint_policy: UncheckedAccount<'info>,
+    /// CHECK: holding lots PDA of source owner
+    #[account(mut, seeds = [b"holding-lots", mint.key().as_ref(), source_token.owner.as_ref()], bump)]
+    pub source_lots: UncheckedAccount<'info>,
+    /// CHECK: holding lots PDA of destination owner
+    #[account(mut, seeds = [b"holding-lots", mint.key().as_ref(), destination_owner.key().as_ref()], bump)]
+    pub destination_lots: UncheckedAccount<'info>,
+    /// CHECK: holder registry PDA, no cap table limits until initialized
+    #[account(mut, seeds = [b"holder-registry", mint.key().as_ref()], bump)]
+    pub holder_registry: UncheckedAccount<'info>,
+    /// CHECK: mint-specific status of source owner, defaults apply until set
+    #[account(seeds = [b"holder-status", mint.key().as_ref(), source_token.owner.as_ref()], bump)]
+    pub source_status: UncheckedAccount<'info>,
+    /// CHECK: mint-specific status of destination owner, defaults apply until set
+    #[account(seeds = [b"holder-status", mint.key().as_ref(), destination_owner.key().as_ref()], bump)]
+    pub destination_status: UncheckedAccount<'info>,
+    /// CHECK: compliance approval for this pair of holders, only needed in a `RiskBands` approval band
+    #[account(
+        mut,
+        seeds = [
+            b"transfer-approval",
+            mint.key().as_ref(),
+            source_token.owner.as_ref(),
+            destination_owner.key().as_ref(),
+        ],
+        bump
+    )]
+    pub transfer_approval: UncheckedAccount<'info>,
+    /// CHECK: holiday calendar PDA, no holidays until initialized
+    #[account(seeds = [b"holiday-calendar", mint.key().as_ref()], bump)]
+    pub holiday_calendar: UncheckedAccount<'info>,
+    /// CHECK: travel rule record for this pair of holders, only needed above the mint's threshold
+    #[account(
+        mut,
+        seeds = [b"travel-rule", mint.key().as_ref(), source_token.owner.as_ref(), destination_owner.key().as_ref()],
+        bump
+    )]
+    pub travel_rule_record: UncheckedAccount<'info>,
+    /// CHECK: transfer journal PDA, transfers are not journaled until initialized
+    #[account(mut, seeds = [b"transfer-journal", mint.key().as_ref()], bump)]
+    pub transfer_journal: UncheckedAccount<'info>,
+    /// CHECK: owner of source_token
+    #[account(address = source_token.owner)]
+    pub source_owner: UncheckedAccount<'info>,
+    /// CHECK: PDA user KYC of authority, only needed for a `DelegateKyc` module
+    #[account(seeds = [b"user-kyc", authority.key().as_ref()], bump)]
+    pub delegate_kyc: UncheckedAccount<'info>,
+    /// CHECK: position PDA of source owner, required while the mint has a holder registry
+    #[account(mut, seeds = [b"holder-position", mint.key().as_ref(), source_token.owner.as_ref()], bump)]
+    pub source_position: UncheckedAccount<'info>,
+    /// CHECK: position PDA of destination owner
+    #[account(mut, seeds = [b"holder-position", mint.key().as_ref(), destination_owner.key().as_ref()], bump)]
+    pub destination_position: UncheckedAccount<'info>,
+}
+
+/// Identity-resolving mints pass the `identity_account_metas` after the fixed accounts
+pub fn handle_transfer_hook<'info>(ctx: Context<'_, '_, 'info, 'info, TransferHook<'info>>, amount: u64) -> Result<()> {
+    let a = &ctx.accounts;
+    require!(
+        is_transferring(&a.source_token.to_account_info())? &&
+            is_transferring(&a.destination_token.to_account_info())?,
+        CustomError::NotTransferring
+    );
+    let accounts = TransferAccounts {
+        mint: &a.mint,
+        source_owner: &a.source_owner,
+        destination_owner: &a.destination_owner,
+        source_kyc: &a.user_kyc,
+        destination_kyc: &a.destination_kyc,
+        exemption_registry: &a.exemption_registry,
+        compliance_config: &a.compliance_config,
+        mint_policy: &a.mint_policy,
+        source_lots: &a.source_lots,
+        destination_lots: &a.destination_lots,
+        holder_registry: &a.holder_registry,
+        source_status: &a.source_status,
+        destination_status: &a.destination_status,
+        transfer_approval: &a.transfer_approval,
+        holiday_calendar: &a.holiday_calendar,
+        travel_rule_record: &a.travel_rule_record,
+        transfer_journal: &a.transfer_journal,
+        source_position: &a.source_position,
+        destination_position: &a.destination_position,
+        identity_accounts: ctx.remaining_accounts,
+        delegate: (a.authority.key() != a.source_owner.key()).then_some(Delegate {
+            authority: &a.authority,
+            kyc: &a.delegate_kyc,
+        }),
+        // the hook runs after the balances have moved
+        source_balance: a.source_token.amount.saturating_add(amount),
+        destination_balance: a.destination_token.amount.saturating_sub(amount),
+    };
+    evaluate_transfer(&accounts, amount, true)
+}
+
+/// Token-2022 sets `transferring` on both token accounts only while its own transfer runs the hook, so
+/// a direct call cannot write lots, counters or the journal
+pub(crate) fn is_transferring(ai: &AccountInfo) -> Result<bool> {
+    let data = ai.try_borrow_data()?;
+    let account = StateWithExtensions::<TokenAccountState>::unpack(&data)?;
+    Ok(account.get_extension::<TransferHookAccount>().is_ok_and(|e| bool::from(e.transferring)))
+}
+
+/// Everything a transfer is evaluated against, shared by the hook and `check_transfer`.
+/// Balances are taken before the transfer.
+struct TransferAccounts<'a, 'info> {
+    mint: &'a InterfaceAccount<'info, Mint>,
+    source_owner: &'a AccountInfo<'info>,
+    destination_owner: &'a AccountInfo<'info>,
+    source_kyc: &'a AccountInfo<'info>,
+    destination_kyc: &'a AccountInfo<'info>,
+    exemption_registry: &'a AccountInfo<'info>,
+    compliance_config: &'a AccountInfo<'info>,
+    mint_policy: &'a AccountInfo<'info>,
+    source_lots: &'a AccountInfo<'info>,
+    destination_lots: &'a AccountInfo<'info>,
+    holder_registry: &'a AccountInfo<'info>,
+    source_status: &'a AccountInfo<'info>,
+    destination_status: &'a AccountInfo<'info>,
+    transfer_approval: &'a AccountInfo<'info>,
+    holiday_calendar: &'a AccountInfo<'info>,
+    travel_rule_record: &'a AccountInfo<'info>,
+    transfer_journal: &'a AccountInfo<'info>,
+    source_position: &'a AccountInfo<'info>,
+    destination_position: &'a AccountInfo<'info>,
+    /// Replace the per-wallet KYC records and positions when the mint resolves identities
+    identity_accounts: &'a [AccountInfo<'info>],
+    /// Set when someone other than the source owner moves the tokens
+    delegate: Option<Delegate<'a, 'info>>,
+    source_balance: u64,
+    destination_balance: u64,
+}
+
+struct Delegate<'a, 'info> {
+    authority: &'a AccountInfo<'info>,
+    kyc: &'a AccountInfo<'info>,
+}
+
+/// The identities both wallets are linked to, their `UserKYC` records and positions
+struct Identities<'a, 'info> {
+    source: Pubkey,
+    destination: Pubkey,
+    source_kyc: &'a AccountInfo<'info>,
+    destination_kyc: &'a AccountInfo<'info>,
+    source_position: &'a AccountInfo<'info>,
+    destination_position: &'a AccountInfo<'info>,
+}
+
+/// The identity accounts in hook order: [24] source_wallet_link, [25] destination_wallet_link,
+/// [26] source_identity, [27] destination_identity, [28] source_identity_position,
+/// [29] destination_identity_position
+fn resolve_identities<'a, 'info>(
+    remaining: &'a [AccountInfo<'info>],
+    source_owner: &Pubkey,
+    destination_owner: &Pubkey
+) -> Result<Identities<'a, 'info>> {
+    let [source_link, destination_link, source_kyc, destination_kyc, source_position, destination_position, ..] =
+        remaining else {
+        return err!(CustomError::IdentityAccountsRequired);
+    };
+    Ok(Identities {
+        source: linked_identity(source_link, source_owner)?,
+        destination: linked_identity(destination_link, destination_owner)?,
+        source_kyc,
+        destination_kyc,
+        source_position,
+        destination_position,
+    })
+}
+
+fn linked_identity(ai: &AccountInfo, wallet: &Pubkey) -> Result<Pubkey> {
+    let link = load_if_initialized::<WalletLink>(ai)?.ok_or(CustomError::WalletNotLinked)?;
+    require_keys_eq!(link.wallet, *wallet, CustomError::WalletNotLinked);
+    Ok(link.identity)
+}
+
+/// A non-exempt side of a transfer
+struct Party {
+    kyc: UserKYC,
+    status: HolderStatus,
+    outgoing: bool,
+}
+
+fn load_party(kyc: &AccountInfo, identity: &Pubkey, status: &AccountInfo, outgoing: bool) -> Result<Party> {
+    Ok(Party {
+        kyc: load_user_kyc(kyc, identity)?,
+        status: load_if_initialized::<HolderStatus>(status)?.unwrap_or_default(),
+        outgoing,
+    })
+}
+
+/// Runs the mint's compliance chain. Sanctions and freezes always apply, everything else comes from
+/// the policy modules in order. Holding lots, volumes, the holder count and the journal are only written
+/// back when `commit` is set.
+fn evaluate_transfer(t: &TransferAccounts, amount: u64, commit: bool) -> Result<()> {
+    let exemptions = load_if_initialized::<ExemptionRegistry>(t.exemption_registry)?;
+    let is_exempt = |ai: &AccountInfo| exemptions.as_ref().is_some_and(|r| r.is_exempt(ai));
+    let grace_period = load_if_initialized::<ComplianceConfig>(t.compliance_config)?.map_or(0, |c| c.grace_period);
+    let policy = load_if_initialized::<MintPolicy>(t.mint_policy)?;
+    let (policy_version, modules, resolves_identities) = policy.map_or_else(
+        || (0, ComplianceModule::default_chain(), false),
+        |p| (p.version, p.modules, p.resolves_identities)
+    );
+    let now = Clock::get()?.unix_timestamp;
+    let identities = match resolves_identities {
+        true => Some(resolve_identities(t.identity_accounts, t.source_owner.key, t.destination_owner.key)?),
+        false => None,
+    };
+    // exemptions and holder status stay per wallet, verification, caps and counters follow the identity
+    let (source_kyc, source_identity, destination_kyc, destination_identity) = match &identities {
+        Some(i) => (i.source_kyc, &i.source, i.destination_kyc, &i.destination),
+        None => (t.source_kyc, t.source_owner.key, t.destination_kyc, t.destination_owner.key),
+    };
+    let (source_position_ai, destination_position_ai) = match &identities {
+        Some(i) => (i.source_position, i.destination_position),
+        None => (t.source_position, t.destination_position),
+    };
+
+    let mut source = match is_exempt(t.source_owner) {
+        true => None,
+        false => Some(load_party(source_kyc, source_identity, t.source_status, true)?),
+    };
+    let mut destination = match is_exempt(t.destination_owner) {
+        true => None,
+        false => Some(load_party(destination_kyc, destination_identity, t.destination_status, false)?),
+    };
+    for party in source.iter().chain(destination.iter()) {
+        check_standing(party)?;
+    }
+    // a wallet's own record keeps its sanctions and freezes when it trades for a linked identity
+    if identities.is_some() {
+        for (kyc, owner) in [(t.source_kyc, t.source_owner), (t.destination_kyc, t.destination_owner)] {
+            if !is_exempt(owner) && kyc.owner == &crate::ID {
+                let kyc = load_user_kyc(kyc, owner.key)?;
+                check_standing(&(Party { kyc, status: HolderStatus::default(), outgoing: false }))?;
+            }
+        }
+    }
+
+    let rwa = if modules.iter().any(|m| matches!(m, ComplianceModule::Geography { .. })) {
+        let mint_ai = t.mint.to_account_info();
+        let data = mint_ai.data.borrow();
+        Token2022MetadataParser::parse_metadata_from_mint(&data)
+            .ok()
+            .map(|meta| Token2022MetadataParser::extract_rwa_metadata(&meta))
+    } else {
+        None
+    };
+
+    // registries count owners, lockups cover all of a holder's accounts and volume and velocity limits are
+    // per mint, so every non-exempt side needs its position
+    let mut registry = load_if_initialized::<HolderRegistry>(t.holder_registry)?;
+    let needs_positions = registry.is_some() || modules.iter().any(ComplianceModule::uses_holder_position);
+    let mut source_position = match (needs_positions, &source) {
+        (true, Some(_)) => Some(load_position(source_position_ai, t.mint, source_identity, t.source_balance)?),
+        _ => None,
+    };
+    let mut destination_position = match (needs_positions, &destination) {
+        (true, Some(_)) => {
+            Some(load_position(destination_position_ai, t.mint, destination_identity, t.destination_balance)?)
+        }
+        _ => None,
+    };
+    // moving between a holder's own accounts changes no position
+    let counts_holders = amount > 0 && source_identity != destination_identity;
+    let source_exits = source_position.as_ref().is_some_and(|p| p.balance == amount);
+    let destination_joins = destination_position.as_ref().is_some_and(|p| p.balance == 0);
+    let mut holders_counted = false;
+    let mut volume_recorded = false;
+    let mut velocity_recorded = false;
+    let mut review_flagged = false;
+
+    for module in &modules {
+        match module {
+            ComplianceModule::KycLevel { min_level } => {
+                for party in source.iter().chain(destination.iter()) {
+                    check_verification(party, *min_level, now, grace_period)?;
+                }
+            }
+            ComplianceModule::Geography { allowed_country_groups } => {
+                for party in source.iter().chain(destination.iter()) {
+                    check_geography(&party.kyc, rwa.as_ref(), *allowed_country_groups)?;
+                }
+            }
+            ComplianceModule::Volume { daily_limit, monthly_limit } => {
+                if let Some(position) = source_position.as_mut() {
+                    record_volume(position, &mut volume_recorded, amount, now, *daily_limit, *monthly_limit)?;
+                }
+            }
+            // lots stay with the holder when moving between their own accounts
+            ComplianceModule::HoldingPeriod { lockup_periods } if source_identity != destination_identity => {
+                let lockup_period = |party: &Party| {
+                    lockup_periods.get(party.status.investor_class(&party.kyc) as usize).copied().unwrap_or(0)
+                };
+                if let (Some(party), Some(position)) = (&source, &source_position) {
+                    update_holding_lots(t.source_lots, commit, |lots| {
+                        lots.release(position.balance, amount, lockup_period(party), now)
+                    })?;
+                }
+                if let Some(party) = &destination {
+                    update_holding_lots(t.destination_lots, commit, |lots| {
+                        lots.record(amount, now, lockup_period(party))
+                    })?;
+                }
+            }
+            ComplianceModule::HoldingPeriod { .. } => {}
+            ComplianceModule::MaxHolders { max_holders, max_holder_bps } => {
+                let registry = registry.as_mut().ok_or(CustomError::HolderRegistryNotInitialized)?;
+                if counts_holders {
+                    registry.apply_transfer(source_exits, destination_joins, *max_holders)?;
+                    holders_counted = true;
+                }
+                if let (true, Some(position)) = (counts_holders, &destination_position) {
+                    HolderRegistry::check_ownership(
+                        position.balance.saturating_add(amount),
+                        t.mint.supply,
+                        *max_holder_bps
+                    )?;
+                }
+            }
+            ComplianceModule::InvestorClass { allowed_classes } => {
+                for party in source.iter().chain(destination.iter()) {
+                    let class = party.status.investor_class(&party.kyc);
+                    require!(
+                        (1u8).checked_shl(class as u32).is_some_and(|bit| allowed_classes & bit != 0),
+                        CustomError::InvestorClassNotAllowed
+                    );
+                }
+            }
+            ComplianceModule::Whitelist => {
+                for party in source.iter().chain(destination.iter()) {
+                    require!(party.status.is_whitelisted(), CustomError::HolderNotWhitelisted);
+                }
+            }
+            ComplianceModule::InvestorRequirements { required_flags } => {
+                if let Some(party) = &destination {
+                    require!(party.kyc.has_investor_flags(*required_flags), CustomError::InvestorRequirementsNotMet);
+                }
+            }
+            ComplianceModule::PoliticallyExposed { min_level, daily_limit, monthly_limit } => {
+                for party in source.iter().chain(destination.iter()) {
+                    require!(
+                        !party.kyc.is_pep() || party.kyc.kyc_level >= *min_level,
+                        CustomError::PepDueDiligenceRequired
+                    );
+                }
+                let source_is_pep = source.as_ref().is_some_and(|p| p.kyc.is_pep());
+                if let (true, Some(position)) = (source_is_pep, source_position.as_mut()) {
+                    record_volume(position, &mut volume_recorded, amount, now, *daily_limit, *monthly_limit)?;
+                }
+            }
+            ComplianceModule::RiskBands { limit_from, max_amount, approval_from, block_from } => {
+                let risk_score = source
+                    .iter()
+                    .chain(destination.iter())
+                    .map(|p| p.kyc.risk_score)
+                    .max()
+                    .unwrap_or(0);
+                require!(risk_score < *block_from, CustomError::RiskScoreBlocked);
+                if risk_score >= *approval_from {
+                    draw_transfer_approval(t.transfer_approval, amount, now, commit)?;
+                } else if risk_score >= *limit_from {
+                    require!(amount <= *max_amount, CustomError::RiskAmountLimitExceeded);
+                }
+            }
+            ComplianceModule::CrossBorder { routes } => {
+                if let (Some(sender), Some(receiver)) = (&source, &destination) {
+                    require!(
+                        routes.iter().any(|r| r.allows(&sender.kyc, &receiver.kyc)),
+                        CustomError::CrossBorderTransferNotAllowed
+                    );
+                }
+            }
+            ComplianceModule::TradingSessions { utc_offset_minutes, windows } => {
+                let holidays = load_if_initialized::<HolidayCalendar>(t.holiday_calendar)?;
+                if !SessionWindow::in_session(windows, *utc_offset_minutes, holidays.as_ref(), now) {
+                    // exempt destinations have no party, their status is still the issuer's to set
+                    let destination_status = load_if_initialized::<HolderStatus>(t.destination_status)?;
+                    require!(
+                        destination_status.is_some_and(|s| s.is_settlement()),
+                        CustomError::OutsideTradingSession
+                    );
+                }
+            }
+            ComplianceModule::TravelRule { threshold } => {
+                if amount >= *threshold {
+                    consume_travel_rule_record(t.travel_rule_record, amount, commit)?;
+                }
+            }
+            ComplianceModule::Velocity { reporting_threshold, near_threshold_bps, review, block, risk_increase } => {
+                if let (Some(party), Some(position)) = (source.as_mut(), source_position.as_mut()) {
+                    let near_from = ((*reporting_threshold as u128) * (*near_threshold_bps as u128) / 10_000) as u64;
+                    position.record_velocity(now, (near_from..*reporting_threshold).contains(&amount));
+                    velocity_recorded = true;
+                    require!(!block.exceeded_by(position, now), CustomError::VelocityLimitExceeded);
+                    let kyc = &mut party.kyc;
+                    if review.reached_by(position, now) && (kyc.flags & UserKYC::FLAG_REVIEW) == 0 {
+                        kyc.flags |= UserKYC::FLAG_REVIEW;
+                        kyc.risk_score = kyc.risk_score.saturating_add(*risk_increase).min(UserKYC::MAX_RISK_SCORE);
+                        review_flagged = true;
+                        if commit {
+                            let [daily_transfer_count, daily_near_threshold_count, monthly_near_threshold_count] =
+                                position.velocity_counts(now);
+                            emit!(EvtVelocityReview {
+                                mint: t.mint.key(),
+                                user: kyc.user,
+                                daily_transfer_count,
+                                daily_near_threshold_count,
+                                monthly_near_threshold_count,
+                                risk_score: kyc.risk_score,
+                            });
+                        }
+                    }
+                }
+            }
+            ComplianceModule::DelegateKyc { min_level } => {
+                if let Some(delegate) = t.delegate.as_ref().filter(|d| !is_exempt(d.authority)) {
+                    // delegates act for the owner, their own record is checked whatever the mint's identities
+                    let party = Party {
+                        kyc: load_user_kyc(delegate.kyc, delegate.authority.key)?,
+                        status: HolderStatus::default(),
+                        outgoing: true,
+                    };
+                    check_standing(&party)?;
+                    check_verification(&party, *min_level, now, grace_period)?;
+                }
+            }
+        }
+    }
+
+    if let Some(registry) = registry.as_mut() {
+        if counts_holders && !holders_counted {
+            registry.apply_transfer(source_exits, destination_joins, 0)?;
+        }
+        if commit && counts_holders {
+            registry.try_serialize(&mut &mut t.holder_registry.try_borrow_mut_data()?[..])?;
+        }
+    }
+    // records count their open positions, so they cannot close while holding any mint that keeps them
+    let mut source_kyc_changed = review_flagged;
+    let mut destination_kyc_changed = false;
+    if commit && (counts_holders || volume_recorded || velocity_recorded) {
+        if let Some(position) = source_position.as_mut() {
+            if counts_holders {
+                let balance = position.balance.saturating_sub(amount);
+                if let Some(party) = source.as_mut() {
+                    source_kyc_changed |= party.kyc.record_position_change(position.balance, balance);
+                }
+                position.balance = balance;
+            }
+            position.try_serialize(&mut &mut source_position_ai.try_borrow_mut_data()?[..])?;
+        }
+    }
+    if let (true, true, Some(position)) = (commit, counts_holders, destination_position.as_mut()) {
+        let balance = position.balance.saturating_add(amount);
+        if let Some(party) = destination.as_mut() {
+            destination_kyc_changed = party.kyc.record_position_change(position.balance, balance);
+        }
+        position.balance = balance;
+        position.try_serialize(&mut &mut destination_position_ai.try_borrow_mut_data()?[..])?;
+    }
+    if let (true, true, Some(party)) = (commit, source_kyc_changed, &source) {
+        store_user_kyc(&party.kyc, source_kyc)?;
+    }
+    if let (true, true, Some(party)) = (commit, destination_kyc_changed, &destination) {
+        store_user_kyc(&party.kyc, destination_kyc)?;
+    }
+    append_to_journal(
+        t.transfer_journal,
+        JournalEntry {
+            sequence: 0,
+            slot: Clock::get()?.slot,
+            amount,
+            source_owner: t.source_owner.key(),
+            destination_owner: t.destination_owner.key(),
+            policy_version,
+            _padding: [0; 4],
+        },
+        commit
+    )
+}
+
+/// Journals are optional, a mint without one is not recorded
+fn append_to_journal(ai: &AccountInfo, entry: JournalEntry, commit: bool) -> Result<()> {
+    if ai.owner != &crate::ID {
+        return Ok(());
+    }
+    let range = 8..8 + TransferJournal::LEN;
+    require!(
+        ai.data_len() >= range.end && ai.try_borrow_data()?.starts_with(TransferJournal::DISCRIMINATOR),
+        ErrorCode::AccountDiscriminatorMismatch
+    );
+    if !commit {
+        let data = ai.try_borrow_data()?;
+        require!(!bytemuck::from_bytes::<TransferJournal>(&data[range]).is_full(), CustomError::TransferJournalFull);
+        return Ok(());
+    }
+    let mut data = ai.try_borrow_mut_data()?;
+    bytemuck::from_bytes_mut::<TransferJournal>(&mut data[range]).append(entry)
+}
+
+/// Counts the transfer once however many modules limit volume, later modules only check the counters
+fn record_volume(
+    position: &mut HolderPosition,
+    recorded: &mut bool,
+    amount: u64,
+    now: i64,
+    daily_limit: u64,
+    monthly_limit: u64
+) -> Result<()> {
+    if *recorded {
+        return position.check_volume(daily_limit, monthly_limit);
+    }
+    *recorded = true;
+    position.record_volume(amount, now, daily_limit, monthly_limit)
+}
+
+/// Deserializes a program-owned PDA, `None` while it has not been created
+fn load_if_initialized<T: AccountDeserialize>(ai: &AccountInfo) -> Result<Option<T>> {
+    if ai.owner != &crate::ID {
+        return Ok(None);
+    }
+    Ok(Some(T::try_deserialize(&mut &ai.try_borrow_data()?[..])?))
+}
+
+fn update_holding_lots(
+    ai: &AccountInfo,
+    commit: bool,
+    f: impl FnOnce(&mut HoldingLots) -> Result<()>
+) -> Result<()> {
+    require!(ai.owner == &crate::ID, CustomError::HoldingLotsNotInitialized);
+    let mut lots = HoldingLots::try_deserialize(&mut &ai.try_borrow_data()?[..])?;
+    f(&mut lots)?;
+    if commit {
+        lots.try_serialize(&mut &mut ai.try_borrow_mut_data()?[..])?;
+    }
+    Ok(())
+}
+
+/// Reconciled with the token account the transfer touches, see `HolderPosition::reconcile`
+fn load_position(ai: &AccountInfo, mint: &InterfaceAccount<Mint>, holder: &Pubkey, seen_balance: u64) -> Result<HolderPosition> {
+    let mut position = load_if_initialized::<HolderPosition>(ai)?.ok_or(CustomError::HolderPositionNotInitialized)?;
+    require!(
+        position.mint == mint.key() && position.holder == *holder,
+        CustomError::HolderPositionNotInitialized
+    );
+    position.reconcile(seen_balance);
+    Ok(position)
+}
+
+fn draw_transfer_approval(ai: &AccountInfo, amount: u64, now: i64, commit: bool) -> Result<()> {
+    let mut approval = load_if_initialized::<TransferApproval>(ai)?.ok_or(CustomError::TransferApprovalRequired)?;
+    approval.draw(amount, now)?;
+    if commit {
+        approval.try_serialize(&mut &mut ai.try_borrow_mut_data()?[..])?;
+    }
+    Ok(())
+}
+
+fn consume_travel_rule_record(ai: &AccountInfo, amount: u64, commit: bool) -> Result<()> {
+    let mut record = load_if_initialized::<TravelRuleRecord>(ai)?.ok_or(CustomError::TravelRuleRecordRequired)?;
+    record.consume(amount)?;
+    if commit {
+        record.try_serialize(&mut &mut ai.try_borrow_mut_data()?[..])?;
+        emit!(EvtConsumeTravelRule {
+            mint: record.mint,
+            source_owner: record.source_owner,
+            destination_owner: record.destination_owner,
+            nonce: record.nonce,
+            amount,
+        });
+    }
+    Ok(())
+}
+
+/// Records created before the latest fields were appended read them as zero, only writing back needs
+/// `migrate_user_kyc` first
+pub(crate) fn load_user_kyc(ai: &AccountInfo, owner: &Pubkey) -> Result<UserKYC> {
+    require!(ai.owner == &crate::ID, CustomError::UserKycNotFound);
+    let mut data = ai.try_borrow_data()?.to_vec();
+    data.resize(data.len().max(8 + UserKYC::LEN), 0);
+    let mut user_kyc = UserKYC::try_deserialize(&mut &data[..])?;
+    user_kyc.migrate_subdivision();
+    require_keys_eq!(user_kyc.user, *owner, CustomError::UserKycNotFound);
+    Ok(user_kyc)
 }
 
-pub fn handle_transfer_hook(ctx: Context<TransferHook>) -> Result<()> {
-    let user_kyc = &ctx.accounts.user_kyc;
+fn store_user_kyc(user_kyc: &UserKYC, ai: &AccountInfo) -> Result<()> {
+    require!(ai.data_len() >= 8 + UserKYC::LEN, CustomError::UserKycMigrationRequired);
+    user_kyc.try_serialize(&mut &mut ai.try_borrow_mut_data()?[..])
+}
 
-    if user_kyc.is_sanctioned() {
+fn check_standing(party: &Party) -> Result<()> {
+    if party.kyc.is_sanctioned() {
         return err!(CustomError::UserSanctioned);
     }
-    if user_kyc.is_frozen() {
+    if party.kyc.is_frozen() {
         return err!(CustomError::UserAccountFrozen);
     }
-    if user_kyc.kyc_level < UserKYC::BASIC {
+    party.status.check()
+}
+
+fn check_verification(party: &Party, min_level: u8, now: i64, grace_period: i64) -> Result<()> {
+    let user_kyc = &party.kyc;
+    if user_kyc.kyc_level < min_level {
         return err!(CustomError::UserNotKycVerified);
     }
-    if user_kyc.is_expired() {
-        return err!(CustomError::UserNotEligible);
+    if user_kyc.is_eligible_for_trading(now, grace_period, party.outgoing) {
+        Ok(())
+    } else if user_kyc.in_grace_period(now, grace_period) {
+        err!(CustomError::UserKycInGracePeriod)
+    } else if user_kyc.is_expired(now) {
+        err!(CustomError::UserKycExpired)
+    } else {
+        err!(CustomError::UserNotKycVerified)
     }
+}
 
-    let mint_ai = ctx.accounts.mint.to_account_info();
-    let data = mint_ai.data.borrow();
-    if let Ok(meta) = Token2022MetadataParser::parse_metadata_from_mint(&data) {
-        let rwa = Token2022MetadataParser::extract_rwa_metadata(&meta);
-        if let Some(allowed) = rwa.allowed_countries {
+fn check_geography(user_kyc: &UserKYC, rwa: Option<&RwaMetadata>, allowed_country_groups: u32) -> Result<()> {
+    if allowed_country_groups != 0 && user_kyc.country_groups() & allowed_country_groups == 0 {
+        return err!(CustomError::InvalidCountryCode);
+    }
+    if let Some(rwa) = rwa {
+        // private records carry no country or state, so per-country rules fail closed for them
+        if let Some(allowed) = &rwa.allowed_countries {
             let uc = user_kyc.get_country_str();
-            if !allowed.contains(&uc) {
+            if user_kyc.is_private || !RwaMetadata::list_contains(allowed, &uc) {
                 return err!(CustomError::InvalidCountryCode);
             }
         }
-        if let Some(restricted) = rwa.restricted_states {
+        if let Some(restricted) = &rwa.restricted_states {
             let code = format!("{}_{}", user_kyc.get_country_str(), user_kyc.get_state_str());
-            if restricted.contains(&code) {
+            if user_kyc.is_private || RwaMetadata::list_contains(restricted, &code) {
                 return err!(CustomError::InvalidStateCode);
             }
         }
+        if let Some(groups) = &rwa.allowed_country_groups {
+            let groups = groups.trim().parse::<u32>().map_err(|_| CustomError::InvalidCountryCode)?;
+            if user_kyc.country_groups() & groups == 0 {
+                return err!(CustomError::InvalidCountryCode);
+            }
+        }
     }
     Ok(())
 }
@@ -73,13 +1003,7 @@ pub struct InitializeExtraAccountMetaList<'info> {
 }
 
 pub fn handle_initialize_extra_account_meta_list(ctx: Context<InitializeExtraAccountMetaList>) -> Result<()> {
-    let metas = vec![
-        ExtraAccountMeta::new_with_seeds(
-            &[Seed::Literal { bytes: b"user-kyc".to_vec() }, Seed::AccountKey { index: 3 }],
-            false,
-            false
-        )?
-    ];
+    let metas = extra_account_metas()?;
     let ai = ctx.accounts.extra_account_meta_list.to_account_info();
     let size = ExtraAccountMetaList::size_of(metas.len())?;
     let lamports = Rent::get()?.minimum_balance(size);
@@ -107,17 +1031,179 @@ pub fn handle_initialize_extra_account_meta_list(ctx: Context<InitializeExtraAcc
 pub struct UpdateExtraAccountMetaList<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
+    /// Transfer hook authority of the mint
+    pub authority: Signer<'info>,
     /// CHECK
-    #[account(mut, seeds = [b"extra-account-metas", mint.key().as_ref()], bump)]
+    #[account(mut, seeds = [b"extra-account-metas", mint.key().as_ref()], bump, owner = crate::ID)]
     pub extra_account_meta_list: AccountInfo<'info>,
     pub mint: InterfaceAccount<'info, Mint>,
-    pub wsol_mint: InterfaceAccount<'info, Mint>,
-    pub token_program: Interface<'info, TokenInterface>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
+    /// CHECK: mint policy PDA, identities are only resolved when it says so
+    #[account(seeds = [b"mint-policy", mint.key().as_ref()], bump)]
+    pub mint_policy: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Keeps identity resolution on for mints that enabled it
+pub fn handle_update_extra_account_meta_list(ctx: Context<UpdateExtraAccountMetaList>) -> Result<()> {
+    let a = &ctx.accounts;
+    let policy = load_if_initialized::<MintPolicy>(&a.mint_policy)?;
+    let resolves_identities = policy.is_some_and(|p| p.resolves_identities);
+    let (ai, mint, authority) = (&a.extra_account_meta_list, &a.mint, &a.authority);
+    rewrite_extra_account_metas(ai, mint, authority, &a.payer, &a.system_program, resolves_identities)
+}
+
+#[derive(Accounts)]
+pub struct SetIdentityResolution<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Transfer hook authority of the mint
+    pub authority: Signer<'info>,
+    /// CHECK
+    #[account(mut, seeds = [b"extra-account-metas", mint.key().as_ref()], bump, owner = crate::ID)]
+    pub extra_account_meta_list: AccountInfo<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, seeds = [b"mint-policy", mint.key().as_ref()], bump = mint_policy.bump)]
+    pub mint_policy: Account<'info, MintPolicy>,
     pub system_program: Program<'info, System>,
 }
 
-pub fn handle_update_extra_account_meta_list(_ctx: Context<UpdateExtraAccountMetaList>) -> Result<()> {
+/// Switches the mint between per-wallet KYC and KYC resolved through wallet links. Positions are keyed by
+/// identity while it is on, so holders need theirs created under the new key before switching.
+pub fn handle_set_identity_resolution(ctx: Context<SetIdentityResolution>, enabled: bool) -> Result<()> {
+    let a = ctx.accounts;
+    let (ai, mint, authority) = (&a.extra_account_meta_list, &a.mint, &a.authority);
+    rewrite_extra_account_metas(ai, mint, authority, &a.payer, &a.system_program, enabled)?;
+    a.mint_policy.resolves_identities = enabled;
+    a.mint_policy.version = a.mint_policy.version.wrapping_add(1);
+    Ok(())
+}
+
+fn rewrite_extra_account_metas<'info>(
+    ai: &AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: &Signer<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    resolve_identities: bool
+) -> Result<()> {
+    require_transfer_hook_authority(&mint.to_account_info(), &authority.key())?;
+
+    let old_metas: Vec<ExtraAccountMetaRecord> = {
+        let data = ai.try_borrow_data()?;
+        let state = TlvStateBorrowed::unpack(&data)?;
+        let list = ExtraAccountMetaList::unpack_with_tlv_state::<ExecuteInstruction>(&state)?;
+        list.data().iter().map(ExtraAccountMetaRecord::from).collect()
+    };
+
+    let mut metas = extra_account_metas()?;
+    if resolve_identities {
+        metas.extend(identity_account_metas()?);
+    }
+    let size = ExtraAccountMetaList::size_of(metas.len())?;
+    // grow before rewriting the TLV entry, shrink only once it has been compacted
+    grow_account(ai, payer, system_program, size)?;
+    ExtraAccountMetaList::update::<ExecuteInstruction>(&mut ai.try_borrow_mut_data()?, &metas)?;
+    if size < ai.data_len() {
+        #[allow(deprecated)]
+        ai.realloc(size, false)?;
+        let surplus = ai.lamports().saturating_sub(Rent::get()?.minimum_balance(size));
+        ai.sub_lamports(surplus)?;
+        payer.add_lamports(surplus)?;
+    }
+
+    emit!(EvtUpdateExtraAccountMetaList {
+        mint: mint.key(),
+        authority: authority.key(),
+        version: EXTRA_ACCOUNT_METAS_VERSION,
+        old_metas,
+        new_metas: metas.iter().map(ExtraAccountMetaRecord::from).collect(),
+    });
+    Ok(())
+}
+
+fn require_transfer_hook_authority(mint: &AccountInfo, authority: &Pubkey) -> Result<()> {
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<MintState>::unpack(&data)?;
+    let hook = mint.get_extension::<TransferHookExtension>().map_err(|_| CustomError::InvalidTransferHookAuthority)?;
+    let hook_authority = Option::<Pubkey>::from(hook.authority).ok_or(CustomError::InvalidTransferHookAuthority)?;
+    require_keys_eq!(hook_authority, *authority, CustomError::InvalidTransferHookAuthority);
+    Ok(())
+}
+
+/// Tops up rent for `size` from `payer` and zero-extends the account if it is smaller
+fn grow_account<'info>(
+    ai: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    size: usize
+) -> Result<()> {
+    top_up_rent(ai, payer, system_program, size)?;
+    if size > ai.data_len() {
+        #[allow(deprecated)]
+        ai.realloc(size, true)?;
+    }
+    Ok(())
+}
+
+/// Creates the PDA `ai` like Anchor's `init`: an address someone already sent lamports to is topped up,
+/// allocated and assigned instead, so pre-funding it cannot block creation
+fn create_pda<'info>(
+    ai: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    space: usize,
+    seeds: &[&[u8]]
+) -> Result<()> {
+    let system = system_program.to_account_info();
+    if ai.lamports() == 0 {
+        return anchor_lang::system_program::create_account(
+            CpiContext::new_with_signer(
+                system,
+                anchor_lang::system_program::CreateAccount { from: payer.to_account_info(), to: ai.clone() },
+                &[seeds]
+            ),
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            &crate::ID
+        );
+    }
+    top_up_rent(ai, payer, system_program, space)?;
+    anchor_lang::system_program::allocate(
+        CpiContext::new_with_signer(
+            system.clone(),
+            anchor_lang::system_program::Allocate { account_to_allocate: ai.clone() },
+            &[seeds]
+        ),
+        space as u64
+    )?;
+    anchor_lang::system_program::assign(
+        CpiContext::new_with_signer(
+            system,
+            anchor_lang::system_program::Assign { account_to_assign: ai.clone() },
+            &[seeds]
+        ),
+        &crate::ID
+    )
+}
+
+/// Funds `ai` for `size` bytes, for accounts another program is about to grow
+fn top_up_rent<'info>(
+    ai: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    size: usize
+) -> Result<()> {
+    let lamports = Rent::get()?.minimum_balance(size);
+    let current = ai.lamports();
+    if lamports > current {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(system_program.to_account_info(), anchor_lang::system_program::Transfer {
+                from: payer.to_account_info(),
+                to: ai.clone(),
+            }),
+            lamports - current
+        )?;
+    }
     Ok(())
 }
 
@@ -135,6 +1221,12 @@ pub struct InitializeUserKyc<'info> {
         bump
     )]
     pub user_kyc: Account<'info, UserKYC>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&payer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
     pub system_program: Program<'info, System>,
 }
 
@@ -145,33 +1237,45 @@ pub fn handle_initialize_user_kyc(
     state: String,
     city: String
 ) -> Result<()> {
-    let user_kyc = &mut ctx.accounts.user_kyc;
-    let clock = Clock::get()?;
+    let now = Clock::get()?.unix_timestamp;
+    let user = ctx.accounts.user.key();
+    let config = &ctx.accounts.compliance_config;
+    fill_new_user_kyc(&mut ctx.accounts.user_kyc, user, kyc_level, &country, &state, &city, now, config)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn fill_new_user_kyc(
+    user_kyc: &mut UserKYC,
+    user: Pubkey,
+    kyc_level: u8,
+    country: &str,
+    state: &str,
+    city: &str,
+    now: i64,
+    config: &ComplianceConfig
+) -> Result<()> {
     require!(kyc_level <= UserKYC::INSTITUTIONAL, crate::error::CustomError::InvalidKycLevel);
-    require!(
-        country.len() == 2 && country.chars().all(|c| c.is_ascii_alphabetic()),
-        crate::error::CustomError::InvalidCountryCode
-    );
-    require!(
-        state.len() <= 2 && state.chars().all(|c| c.is_ascii_alphanumeric()),
-        crate::error::CustomError::InvalidStateCode
-    );
+    let (country, state) = (country.to_uppercase(), state.to_uppercase());
+    UserKYC::validate_country(&country)?;
+    UserKYC::validate_subdivision(&country, &state)?;
     require!(
         city.len() <= 32 && city.chars().all(|c| c.is_ascii() && !c.is_ascii_control()),
         crate::error::CustomError::InvalidCityName
     );
-    user_kyc.user = ctx.accounts.user.key();
+    user_kyc.user = user;
     user_kyc.kyc_level = kyc_level;
     user_kyc.risk_score = 50;
-    user_kyc.last_updated = clock.unix_timestamp;
+    user_kyc.last_updated = now;
     user_kyc.flags = 0;
     user_kyc.daily_volume = 0;
     user_kyc.monthly_volume = 0;
-    user_kyc.last_reset_day = clock.unix_timestamp / 86400;
-    user_kyc.last_reset_month = clock.unix_timestamp / (86400 * 30);
-    user_kyc.set_country(&country.to_uppercase());
-    user_kyc.set_state(&state.to_uppercase());
-    user_kyc.set_city(&city);
+    user_kyc.last_reset_day = now / 86400;
+    user_kyc.last_reset_month = now / (86400 * 30);
+    user_kyc.set_country(&country);
+    user_kyc.set_state(&state);
+    user_kyc.set_city(city);
+    user_kyc.verify(now, config.validity_period(kyc_level));
+    user_kyc.version = UserKYC::VERSION;
     Ok(())
 }
 
@@ -182,49 +1286,57 @@ pub struct UpdateUserKyc<'info> {
     pub user: UncheckedAccount<'info>,
     #[account(mut, seeds = [b"user-kyc", user.key().as_ref()], bump)]
     pub user_kyc: Account<'info, UserKYC>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&authority.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn handle_update_user_kyc(
-    ctx: Context<UpdateUserKyc>,
-    new_kyc_level: Option<u8>,
-    new_risk_score: Option<u8>,
-    flags_to_set: Option<u8>,
-    flags_to_clear: Option<u8>,
-    new_country: Option<String>,
-    new_state: Option<String>,
-    new_city: Option<String>
-) -> Result<()> {
+pub fn handle_update_user_kyc(ctx: Context<UpdateUserKyc>, update: UserKycUpdate) -> Result<()> {
     let user_kyc = &mut ctx.accounts.user_kyc;
     let clock = Clock::get()?;
-    if let Some(level) = new_kyc_level {
+    require!(
+        !user_kyc.is_private || (update.country.is_none() && update.state.is_none() && update.city.is_none()),
+        CustomError::PrivateKycRecord
+    );
+    require!(
+        !ctx.accounts.compliance_config.requires_dual_control(
+            user_kyc,
+            update.kyc_level,
+            update.flags_to_clear.unwrap_or(0)
+        ),
+        CustomError::DualControlRequired
+    );
+    if let Some(level) = update.kyc_level {
         require!(level <= UserKYC::INSTITUTIONAL, crate::error::CustomError::InvalidKycLevel);
+        if level != user_kyc.kyc_level {
+            user_kyc.verify(clock.unix_timestamp, ctx.accounts.compliance_config.validity_period(level));
+        }
         user_kyc.kyc_level = level;
     }
-    if let Some(score) = new_risk_score {
+    if let Some(score) = update.risk_score {
+        require!(score <= UserKYC::MAX_RISK_SCORE, CustomError::InvalidRiskScore);
         user_kyc.risk_score = score;
     }
-    if let Some(set) = flags_to_set {
+    if let Some(set) = update.flags_to_set {
         user_kyc.flags |= set;
     }
-    if let Some(clear) = flags_to_clear {
+    if let Some(clear) = update.flags_to_clear {
         user_kyc.flags &= !clear;
     }
-    if let Some(country) = new_country {
-        require!(
-            country.len() == 2 && country.chars().all(|c| c.is_ascii_alphabetic()),
-            crate::error::CustomError::InvalidCountryCode
-        );
-        user_kyc.set_country(&country.to_uppercase());
-    }
-    if let Some(state) = new_state {
-        require!(
-            state.len() <= 2 && state.chars().all(|c| c.is_ascii_alphanumeric()),
-            crate::error::CustomError::InvalidStateCode
-        );
-        user_kyc.set_state(&state.to_uppercase());
+    if update.country.is_some() || update.state.is_some() {
+        // a new country can invalidate the stored subdivision, so the pair is checked as a whole
+        let country = update.country.map_or_else(|| user_kyc.get_country_str(), |c| c.to_uppercase());
+        let state = update.state.map_or_else(|| user_kyc.get_state_str(), |s| s.to_uppercase());
+        UserKYC::validate_country(&country)?;
+        UserKYC::validate_subdivision(&country, &state)?;
+        user_kyc.set_country(&country);
+        user_kyc.set_state(&state);
     }
-    if let Some(city) = new_city {
+    if let Some(city) = update.city {
         require!(
             city.len() <= 32 && city.chars().all(|c| c.is_ascii() && !c.is_ascii_control()),
             crate::error::CustomError::InvalidCityName
@@ -234,3 +1346,1936 @@ pub fn handle_update_user_kyc(
     user_kyc.last_updated = clock.unix_timestamp;
     Ok(())
 }
+
+#[derive(Accounts)]
+pub struct InitializeComplianceConfig<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    /// The first authority must be whoever can upgrade this program, otherwise anyone could claim the
+    /// config before the deployer does
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ CustomError::Unauthorized)]
+    pub program: Program<'info, crate::program::TransferHook>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ CustomError::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(init, payer = payer, space = 8 + ComplianceConfig::LEN, seeds = [b"compliance-config"], bump)]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    #[account(init, payer = payer, space = 8 + ExemptionRegistry::LEN, seeds = [b"exemption-registry"], bump)]
+    pub exemption_registry: Account<'info, ExemptionRegistry>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_initialize_compliance_config(ctx: Context<InitializeComplianceConfig>) -> Result<()> {
+    let config = &mut ctx.accounts.compliance_config;
+    config.authority = ctx.accounts.authority.key();
+    config.officers = Vec::new();
+    config.kyc_validity = ComplianceConfig::DEFAULT_KYC_VALIDITY;
+    config.grace_period = 0;
+    config.bump = ctx.bumps.compliance_config;
+    config.dual_control_level = 0;
+    config.pending_change_ttl = 0;
+    let registry = &mut ctx.accounts.exemption_registry;
+    registry.entries = Vec::new();
+    registry.bump = ctx.bumps.exemption_registry;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ManageComplianceConfig<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        has_one = authority @ CustomError::Unauthorized
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+}
+
+pub fn handle_update_compliance_authority(ctx: Context<ManageComplianceConfig>, new_authority: Pubkey) -> Result<()> {
+    ctx.accounts.compliance_config.authority = new_authority;
+    Ok(())
+}
+
+pub fn handle_add_kyc_officer(ctx: Context<ManageComplianceConfig>, officer: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.compliance_config;
+    require!(config.officers.len() < ComplianceConfig::MAX_OFFICERS, CustomError::OfficerListFull);
+    require!(!config.officers.contains(&officer), CustomError::OfficerAlreadyExists);
+    config.officers.push(officer);
+    Ok(())
+}
+
+pub fn handle_remove_kyc_officer(ctx: Context<ManageComplianceConfig>, officer: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.compliance_config;
+    let index = config.officers
+        .iter()
+        .position(|o| *o == officer)
+        .ok_or(CustomError::OfficerNotFound)?;
+    config.officers.remove(index);
+    Ok(())
+}
+
+pub fn handle_set_kyc_validity(
+    ctx: Context<ManageComplianceConfig>,
+    kyc_validity: [i64; 4],
+    grace_period: i64
+) -> Result<()> {
+    require!(kyc_validity.iter().all(|v| *v >= 0) && grace_period >= 0, CustomError::InvalidValidityPeriod);
+    let config = &mut ctx.accounts.compliance_config;
+    config.kyc_validity = kyc_validity;
+    config.grace_period = grace_period;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ManageExemption<'info> {
+    pub authority: Signer<'info>,
+    #[account(seeds = [b"compliance-config"], bump = compliance_config.bump, has_one = authority @ CustomError::Unauthorized)]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    #[account(mut, seeds = [b"exemption-registry"], bump = exemption_registry.bump)]
+    pub exemption_registry: Account<'info, ExemptionRegistry>,
+}
+
+pub fn handle_add_exemption(ctx: Context<ManageExemption>, key: Pubkey, kind: u8) -> Result<()> {
+    require!(
+        kind == ExemptionRegistry::KIND_OWNER || kind == ExemptionRegistry::KIND_PROGRAM,
+        CustomError::InvalidExemption
+    );
+    // exempting by system program would exempt every wallet
+    require!(
+        key != Pubkey::default() && !(kind == ExemptionRegistry::KIND_PROGRAM && key == System::id()),
+        CustomError::InvalidExemption
+    );
+    ctx.accounts.exemption_registry.add(key, kind)?;
+    emit!(EvtAddExemption { authority: ctx.accounts.authority.key(), key, kind });
+    Ok(())
+}
+
+pub fn handle_remove_exemption(ctx: Context<ManageExemption>, key: Pubkey) -> Result<()> {
+    ctx.accounts.exemption_registry.remove(key)?;
+    emit!(EvtRemoveExemption { authority: ctx.accounts.authority.key(), key });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RenewKyc<'info> {
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    /// CHECK
+    pub user: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"user-kyc", user.key().as_ref()], bump)]
+    pub user_kyc: Account<'info, UserKYC>,
+}
+
+pub fn handle_renew_kyc(ctx: Context<RenewKyc>, new_kyc_level: Option<u8>) -> Result<()> {
+    let user_kyc = &mut ctx.accounts.user_kyc;
+    let now = Clock::get()?.unix_timestamp;
+    require!(
+        !ctx.accounts.compliance_config.requires_dual_control(user_kyc, new_kyc_level, 0),
+        CustomError::DualControlRequired
+    );
+    if let Some(level) = new_kyc_level {
+        require!(level <= UserKYC::INSTITUTIONAL, CustomError::InvalidKycLevel);
+        user_kyc.kyc_level = level;
+    }
+    require!(user_kyc.kyc_level >= UserKYC::BASIC, CustomError::UserNotKycVerified);
+    let validity_period = ctx.accounts.compliance_config.validity_period(user_kyc.kyc_level);
+    user_kyc.verify(now, validity_period);
+    user_kyc.last_updated = now;
+    emit!(EvtRenewKyc {
+        officer: ctx.accounts.officer.key(),
+        user: user_kyc.user,
+        kyc_level: user_kyc.kyc_level,
+        verified_at: user_kyc.verified_at,
+        expires_at: user_kyc.expires_at,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeKycProviderRegistry<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    #[account(seeds = [b"compliance-config"], bump = compliance_config.bump, has_one = authority @ CustomError::Unauthorized)]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    #[account(init, payer = payer, space = 8 + KycProviderRegistry::LEN, seeds = [b"kyc-provider-registry"], bump)]
+    pub kyc_provider_registry: Account<'info, KycProviderRegistry>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_initialize_kyc_provider_registry(ctx: Context<InitializeKycProviderRegistry>) -> Result<()> {
+    let registry = &mut ctx.accounts.kyc_provider_registry;
+    registry.providers = Vec::new();
+    registry.bump = ctx.bumps.kyc_provider_registry;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ManageKycProvider<'info> {
+    pub authority: Signer<'info>,
+    #[account(seeds = [b"compliance-config"], bump = compliance_config.bump, has_one = authority @ CustomError::Unauthorized)]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    #[account(mut, seeds = [b"kyc-provider-registry"], bump = kyc_provider_registry.bump)]
+    pub kyc_provider_registry: Account<'info, KycProviderRegistry>,
+}
+
+pub fn handle_add_kyc_provider(ctx: Context<ManageKycProvider>, signing_key: Pubkey, max_kyc_level: u8) -> Result<()> {
+    require!(
+        (UserKYC::BASIC..=UserKYC::INSTITUTIONAL).contains(&max_kyc_level),
+        CustomError::InvalidKycLevel
+    );
+    let registry = &mut ctx.accounts.kyc_provider_registry;
+    require!(registry.providers.len() < KycProviderRegistry::MAX_PROVIDERS, CustomError::ProviderRegistryFull);
+    require!(registry.get(&signing_key).is_none(), CustomError::ProviderAlreadyExists);
+    registry.providers.push(KycProvider { signing_key, max_kyc_level });
+    Ok(())
+}
+
+pub fn handle_remove_kyc_provider(ctx: Context<ManageKycProvider>, signing_key: Pubkey) -> Result<()> {
+    let registry = &mut ctx.accounts.kyc_provider_registry;
+    let index = registry.providers
+        .iter()
+        .position(|p| p.signing_key == signing_key)
+        .ok_or(CustomError::ProviderNotFound)?;
+    registry.providers.remove(index);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SubmitKycAttestation<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + UserKYC::LEN,
+        seeds = [b"user-kyc", user.key().as_ref()],
+        bump
+    )]
+    pub user_kyc: Account<'info, UserKYC>,
+    #[account(seeds = [b"kyc-provider-registry"], bump = kyc_provider_registry.bump)]
+    pub kyc_provider_registry: Account<'info, KycProviderRegistry>,
+    /// CHECK: instructions sysvar
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_submit_kyc_attestation(ctx: Context<SubmitKycAttestation>, attestation: KycAttestation) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let provider = attestation_signer(&load_ed25519_instruction(&ctx.accounts.instructions_sysvar)?, &attestation)?;
+    let provider_info = ctx.accounts.kyc_provider_registry.get(&provider).ok_or(CustomError::ProviderNotFound)?;
+
+    require_keys_eq!(attestation.user, ctx.accounts.user.key(), CustomError::InvalidAttestation);
+    require!(
+        (UserKYC::BASIC..=provider_info.max_kyc_level).contains(&attestation.kyc_level),
+        CustomError::InvalidKycLevel
+    );
+    require!(crate::iso3166::is_country(&attestation.country), CustomError::InvalidCountryCode);
+    let state = String::from_utf8(attestation.state.to_vec()).map_err(|_| CustomError::InvalidStateCode)?;
+    let state = state.trim_end_matches('\0');
+    require!(attestation.issued_at <= now, CustomError::InvalidAttestation);
+    require!(attestation.expires_at > now, CustomError::AttestationExpired);
+
+    UserKYC::validate_subdivision(&String::from_utf8_lossy(&attestation.country), state)?;
+
+    let user_kyc = &mut ctx.accounts.user_kyc;
+    if user_kyc.user == Pubkey::default() {
+        user_kyc.user = attestation.user;
+        user_kyc.version = UserKYC::VERSION;
+        user_kyc.risk_score = 50;
+        user_kyc.flags = 0;
+        user_kyc.last_reset_day = now / 86400;
+        user_kyc.last_reset_month = now / (86400 * 30);
+    } else {
+        require!(attestation.issued_at > user_kyc.verified_at, CustomError::StaleAttestation);
+    }
+    user_kyc.kyc_level = attestation.kyc_level;
+    if user_kyc.is_private {
+        user_kyc.country_group_mask = CountryGroups::of(&attestation.country);
+    } else {
+        user_kyc.country = attestation.country;
+        user_kyc.set_state(state);
+    }
+    user_kyc.verified_at = attestation.issued_at;
+    user_kyc.expires_at = attestation.expires_at;
+    user_kyc.flags &= !UserKYC::FLAG_EXPIRED;
+    user_kyc.last_updated = now;
+
+    emit!(EvtKycAttested {
+        user: attestation.user,
+        provider,
+        kyc_level: attestation.kyc_level,
+        issued_at: attestation.issued_at,
+        expires_at: attestation.expires_at,
+    });
+    Ok(())
+}
+
+/// Data of the Ed25519 program instruction placed right before this one
+fn load_ed25519_instruction(instructions_sysvar: &AccountInfo) -> Result<Vec<u8>> {
+    let current = load_current_index_checked(instructions_sysvar)?;
+    require!(current > 0, CustomError::InvalidEd25519Instruction);
+    let ix = load_instruction_at_checked((current - 1) as usize, instructions_sysvar)?;
+    require_keys_eq!(ix.program_id, ed25519_program::ID, CustomError::InvalidEd25519Instruction);
+    Ok(ix.data)
+}
+
+/// Provider whose signature in the Ed25519 instruction `data` covers exactly `attestation`
+pub(crate) fn attestation_signer(data: &[u8], attestation: &KycAttestation) -> Result<Pubkey> {
+    let (provider, message) = parse_ed25519_signature(data)?;
+    require!(message == attestation.message()?, CustomError::InvalidAttestation);
+    Ok(provider)
+}
+
+/// Reads the single signature of an Ed25519 program instruction, returning the signer and the signed
+/// message
+pub(crate) fn parse_ed25519_signature(data: &[u8]) -> Result<(Pubkey, &[u8])> {
+    // [num_signatures: u8, padding: u8, offsets: 7 x u16]
+    require!(data.len() >= 16 && data[0] == 1, CustomError::InvalidEd25519Instruction);
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let public_key_offset = read_u16(6) as usize;
+    let message_offset = read_u16(10) as usize;
+    let message_size = read_u16(12) as usize;
+    // all signature data must live in the Ed25519 instruction itself
+    require!(
+        read_u16(4) == u16::MAX && read_u16(8) == u16::MAX && read_u16(14) == u16::MAX,
+        CustomError::InvalidEd25519Instruction
+    );
+
+    let public_key = data.get(public_key_offset..public_key_offset + 32).ok_or(CustomError::InvalidEd25519Instruction)?;
+    let message = data
+        .get(message_offset..message_offset + message_size)
+        .ok_or(CustomError::InvalidEd25519Instruction)?;
+    Ok((Pubkey::try_from(public_key).map_err(|_| CustomError::InvalidEd25519Instruction)?, message))
+}
+
+#[derive(Accounts)]
+pub struct InitializePrivateUserKyc<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK
+    pub user: UncheckedAccount<'info>,
+    #[account(init, payer = payer, space = 8 + UserKYC::LEN, seeds = [b"user-kyc", user.key().as_ref()], bump)]
+    pub user_kyc: Account<'info, UserKYC>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&payer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_initialize_private_user_kyc(
+    ctx: Context<InitializePrivateUserKyc>,
+    kyc_level: u8,
+    identity_hash: [u8; 32],
+    country_group_mask: u32
+) -> Result<()> {
+    require!(kyc_level <= UserKYC::INSTITUTIONAL, CustomError::InvalidKycLevel);
+    require!(country_group_mask != 0, CustomError::InvalidCountryCode);
+    let user_kyc = &mut ctx.accounts.user_kyc;
+    let now = Clock::get()?.unix_timestamp;
+    user_kyc.user = ctx.accounts.user.key();
+    user_kyc.kyc_level = kyc_level;
+    user_kyc.risk_score = 50;
+    user_kyc.last_updated = now;
+    user_kyc.last_reset_day = now / 86400;
+    user_kyc.last_reset_month = now / (86400 * 30);
+    user_kyc.make_private(identity_hash, country_group_mask);
+    user_kyc.verify(now, ctx.accounts.compliance_config.validity_period(kyc_level));
+    user_kyc.version = UserKYC::VERSION;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct MigrateUserKyc<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK
+    pub user: UncheckedAccount<'info>,
+    /// CHECK: may still use an older layout, validated in the handler
+    #[account(mut, seeds = [b"user-kyc", user.key().as_ref()], bump, owner = crate::ID)]
+    pub user_kyc: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_migrate_user_kyc(ctx: Context<MigrateUserKyc>) -> Result<()> {
+    let ai = ctx.accounts.user_kyc.to_account_info();
+    let old_len = ai.data_len();
+    require!(ai.try_borrow_data()?.starts_with(UserKYC::DISCRIMINATOR), CustomError::UserKycNotFound);
+    grow_account(&ai, &ctx.accounts.payer, &ctx.accounts.system_program, 8 + UserKYC::LEN)?;
+
+    let mut user_kyc = UserKYC::try_deserialize(&mut &ai.try_borrow_data()?[..])?;
+    user_kyc.migrate_subdivision();
+    user_kyc.version = UserKYC::VERSION;
+    user_kyc.try_serialize(&mut &mut ai.try_borrow_mut_data()?[..])?;
+
+    emit!(EvtMigrateUserKyc { user: user_kyc.user, old_len: old_len as u64, version: user_kyc.version });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct PrivatizeUserKyc<'info> {
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    /// CHECK
+    pub user: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"user-kyc", user.key().as_ref()], bump)]
+    pub user_kyc: Account<'info, UserKYC>,
+}
+
+/// Converts a plaintext record; country groups default to those of the stored country
+pub fn handle_privatize_user_kyc(
+    ctx: Context<PrivatizeUserKyc>,
+    identity_hash: [u8; 32],
+    country_group_mask: Option<u32>
+) -> Result<()> {
+    let user_kyc = &mut ctx.accounts.user_kyc;
+    let country_group_mask = country_group_mask.unwrap_or(user_kyc.country_groups());
+    require!(country_group_mask != 0, CustomError::InvalidCountryCode);
+    user_kyc.make_private(identity_hash, country_group_mask);
+    user_kyc.last_updated = Clock::get()?.unix_timestamp;
+    emit!(EvtPrivatizeUserKyc {
+        officer: ctx.accounts.officer.key(),
+        user: user_kyc.user,
+        identity_hash,
+        country_group_mask,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeMintPolicy<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Transfer hook authority of the mint
+    pub authority: Signer<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        space = MintPolicy::space(&ComplianceModule::default_chain()),
+        seeds = [b"mint-policy", mint.key().as_ref()],
+        bump
+    )]
+    pub mint_policy: Account<'info, MintPolicy>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_initialize_mint_policy(ctx: Context<InitializeMintPolicy>) -> Result<()> {
+    require_transfer_hook_authority(&ctx.accounts.mint.to_account_info(), &ctx.accounts.authority.key())?;
+    let policy = &mut ctx.accounts.mint_policy;
+    policy.mint = ctx.accounts.mint.key();
+    policy.authority = ctx.accounts.authority.key();
+    policy.bump = ctx.bumps.mint_policy;
+    policy.modules = ComplianceModule::default_chain();
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(modules: Vec<ComplianceModule>)]
+pub struct SetComplianceModules<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"mint-policy", mint_policy.mint.as_ref()],
+        bump = mint_policy.bump,
+        has_one = authority @ CustomError::Unauthorized,
+        realloc = MintPolicy::space(&modules),
+        realloc::payer = payer,
+        realloc::zero = false
+    )]
+    pub mint_policy: Account<'info, MintPolicy>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Replaces the whole chain. A holding period only covers lots received while it is set,
+/// earlier balances count as unlocked.
+pub fn handle_set_compliance_modules(ctx: Context<SetComplianceModules>, modules: Vec<ComplianceModule>) -> Result<()> {
+    MintPolicy::validate_modules(&modules)?;
+    let policy = &mut ctx.accounts.mint_policy;
+    policy.modules = modules;
+    policy.version = policy.version.wrapping_add(1);
+    emit!(EvtSetComplianceModules {
+        mint: policy.mint,
+        authority: ctx.accounts.authority.key(),
+        modules: policy.modules.clone(),
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ManageMintPolicy<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"mint-policy", mint_policy.mint.as_ref()],
+        bump = mint_policy.bump,
+        has_one = authority @ CustomError::Unauthorized
+    )]
+    pub mint_policy: Account<'info, MintPolicy>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Sets the `HoldingPeriod` module, all zero removes it. Lockups only apply to lots received while they
+/// are set, earlier balances are treated as unlocked.
+pub fn handle_set_lockup_periods(ctx: Context<ManageMintPolicy>, lockup_periods: [i64; 4]) -> Result<()> {
+    let module = ComplianceModule::HoldingPeriod { lockup_periods };
+    let a = ctx.accounts;
+    update_module(&mut a.mint_policy, &a.authority, &a.payer, &a.system_program, module, lockup_periods != [0; 4])?;
+    emit!(EvtSetLockupPeriods { mint: a.mint_policy.mint, authority: a.authority.key(), lockup_periods });
+    Ok(())
+}
+
+/// Adds or removes the `Whitelist` module
+pub fn handle_set_whitelist_required(ctx: Context<ManageMintPolicy>, whitelist_required: bool) -> Result<()> {
+    let a = ctx.accounts;
+    update_module(
+        &mut a.mint_policy,
+        &a.authority,
+        &a.payer,
+        &a.system_program,
+        ComplianceModule::Whitelist,
+        whitelist_required
+    )?;
+    emit!(EvtSetWhitelistRequired { mint: a.mint_policy.mint, authority: a.authority.key(), whitelist_required });
+    Ok(())
+}
+
+/// Sets (`enabled`) or removes the module of `module`'s kind, keeping the rest of the chain and its order
+fn update_module<'info>(
+    policy: &mut Account<'info, MintPolicy>,
+    authority: &Signer<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    module: ComplianceModule,
+    enabled: bool
+) -> Result<()> {
+    if enabled {
+        policy.set_module(module);
+    } else {
+        policy.remove_module(&module);
+    }
+    MintPolicy::validate_modules(&policy.modules)?;
+    policy.version = policy.version.wrapping_add(1);
+    grow_account(&policy.to_account_info(), payer, system_program, MintPolicy::space(&policy.modules))?;
+    emit!(EvtSetComplianceModules { mint: policy.mint, authority: authority.key(), modules: policy.modules.clone() });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetHolderStatus<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"mint-policy", mint_policy.mint.as_ref()],
+        bump = mint_policy.bump,
+        has_one = authority @ CustomError::Unauthorized
+    )]
+    pub mint_policy: Account<'info, MintPolicy>,
+    /// CHECK
+    pub holder: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + HolderStatus::LEN,
+        seeds = [b"holder-status", mint_policy.mint.as_ref(), holder.key().as_ref()],
+        bump
+    )]
+    pub holder_status: Account<'info, HolderStatus>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_set_holder_status(ctx: Context<SetHolderStatus>, flags: u8, investor_class: Option<u8>) -> Result<()> {
+    HolderStatus::validate_flags(flags)?;
+    require!(
+        !matches!(investor_class, Some(c) if c > UserKYC::CLASS_AFFILIATE),
+        CustomError::InvalidInvestorClass
+    );
+    let status = &mut ctx.accounts.holder_status;
+    status.mint = ctx.accounts.mint_policy.mint;
+    status.holder = ctx.accounts.holder.key();
+    status.flags = flags;
+    status.investor_class = investor_class;
+    status.bump = ctx.bumps.holder_status;
+    emit!(EvtSetHolderStatus {
+        mint: status.mint,
+        authority: ctx.accounts.authority.key(),
+        holder: status.holder,
+        flags,
+        investor_class,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeHoldingLots<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK
+    pub owner: UncheckedAccount<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + HoldingLots::LEN,
+        seeds = [b"holding-lots", mint.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub holding_lots: Account<'info, HoldingLots>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_initialize_holding_lots(ctx: Context<InitializeHoldingLots>) -> Result<()> {
+    let lots = &mut ctx.accounts.holding_lots;
+    lots.mint = ctx.accounts.mint.key();
+    lots.owner = ctx.accounts.owner.key();
+    lots.lots = Vec::new();
+    lots.bump = ctx.bumps.holding_lots;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetInvestorClass<'info> {
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    /// CHECK
+    pub user: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"user-kyc", user.key().as_ref()], bump)]
+    pub user_kyc: Account<'info, UserKYC>,
+}
+
+pub fn handle_set_investor_class(ctx: Context<SetInvestorClass>, investor_class: u8) -> Result<()> {
+    require!(investor_class <= UserKYC::CLASS_AFFILIATE, CustomError::InvalidInvestorClass);
+    let user_kyc = &mut ctx.accounts.user_kyc;
+    user_kyc.investor_class = investor_class;
+    user_kyc.last_updated = Clock::get()?.unix_timestamp;
+    emit!(EvtSetInvestorClass { officer: ctx.accounts.officer.key(), user: user_kyc.user, investor_class });
+    Ok(())
+}
+
+pub fn handle_set_investor_flags(ctx: Context<SetInvestorClass>, investor_flags: u8) -> Result<()> {
+    UserKYC::validate_investor_flags(investor_flags)?;
+    let user_kyc = &mut ctx.accounts.user_kyc;
+    user_kyc.investor_flags = investor_flags;
+    user_kyc.last_updated = Clock::get()?.unix_timestamp;
+    emit!(EvtSetInvestorFlags { officer: ctx.accounts.officer.key(), user: user_kyc.user, investor_flags });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeHolderRegistry<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"mint-policy", mint_policy.mint.as_ref()],
+        bump = mint_policy.bump,
+        has_one = authority @ CustomError::Unauthorized
+    )]
+    pub mint_policy: Account<'info, MintPolicy>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + HolderRegistry::LEN,
+        seeds = [b"holder-registry", mint_policy.mint.as_ref()],
+        bump
+    )]
+    pub holder_registry: Account<'info, HolderRegistry>,
+    pub system_program: Program<'info, System>,
+}
+
+/// `holder_count` seeds the registry with holders that existed before it, counted off-chain.
+/// The caps go into the `MaxHolders` compliance module.
+pub fn handle_initialize_holder_registry(
+    ctx: Context<InitializeHolderRegistry>,
+    holder_count: u32,
+    max_holders: u32,
+    max_holder_bps: u16
+) -> Result<()> {
+    let a = ctx.accounts;
+    let registry = &mut a.holder_registry;
+    registry.mint = a.mint_policy.mint;
+    registry.holder_count = holder_count;
+    registry.bump = ctx.bumps.holder_registry;
+    let module = ComplianceModule::MaxHolders { max_holders, max_holder_bps };
+    update_module(&mut a.mint_policy, &a.authority, &a.payer, &a.system_program, module, true)?;
+    emit!(EvtSetHolderLimits { mint: registry.mint, authority: a.authority.key(), max_holders, max_holder_bps });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ManageHolderRegistry<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"mint-policy", mint_policy.mint.as_ref()],
+        bump = mint_policy.bump,
+        has_one = authority @ CustomError::Unauthorized
+    )]
+    pub mint_policy: Account<'info, MintPolicy>,
+    #[account(seeds = [b"holder-registry", mint_policy.mint.as_ref()], bump = holder_registry.bump)]
+    pub holder_registry: Account<'info, HolderRegistry>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Sets the `MaxHolders` module. Lowering a cap below the current count only blocks new holders,
+/// existing ones keep their balances.
+pub fn handle_set_holder_limits(
+    ctx: Context<ManageHolderRegistry>,
+    max_holders: u32,
+    max_holder_bps: u16
+) -> Result<()> {
+    let a = ctx.accounts;
+    let module = ComplianceModule::MaxHolders { max_holders, max_holder_bps };
+    update_module(&mut a.mint_policy, &a.authority, &a.payer, &a.system_program, module, true)?;
+    emit!(EvtSetHolderLimits { mint: a.mint_policy.mint, authority: a.authority.key(), max_holders, max_holder_bps });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeHolderPosition<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK
+    pub holder: UncheckedAccount<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + HolderPosition::LEN,
+        seeds = [b"holder-position", mint.key().as_ref(), holder.key().as_ref()],
+        bump
+    )]
+    pub holder_position: Account<'info, HolderPosition>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Starts at zero, the hook takes up a balance held before the position from the first token account it sees
+pub fn handle_initialize_holder_position(ctx: Context<InitializeHolderPosition>) -> Result<()> {
+    let position = &mut ctx.accounts.holder_position;
+    position.mint = ctx.accounts.mint.key();
+    position.holder = ctx.accounts.holder.key();
+    position.balance = 0;
+    position.bump = ctx.bumps.holder_position;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SyncHolderPosition<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"mint-policy", mint_policy.mint.as_ref()],
+        bump = mint_policy.bump,
+        has_one = authority @ CustomError::Unauthorized
+    )]
+    pub mint_policy: Account<'info, MintPolicy>,
+    #[account(mut, seeds = [b"holder-registry", mint_policy.mint.as_ref()], bump = holder_registry.bump)]
+    pub holder_registry: Option<Account<'info, HolderRegistry>>,
+    #[account(
+        mut,
+        seeds = [b"holder-position", mint_policy.mint.as_ref(), holder_position.holder.as_ref()],
+        bump = holder_position.bump
+    )]
+    pub holder_position: Account<'info, HolderPosition>,
+    /// CHECK: the holder's `UserKYC`, absent for exempt holders, counts the position while it is open
+    #[account(mut, seeds = [b"user-kyc", holder_position.holder.as_ref()], bump)]
+    pub user_kyc: UncheckedAccount<'info>,
+}
+
+/// Records a balance change made outside a transfer: issuance, redemption, or holdings spread over several
+/// accounts before the position existed. Issuance is the issuer's call, so the holder cap does not apply.
+pub fn handle_sync_holder_position(ctx: Context<SyncHolderPosition>, balance: u64) -> Result<()> {
+    let position = &mut ctx.accounts.holder_position;
+    let old_balance = position.balance;
+    if let Some(registry) = ctx.accounts.holder_registry.as_mut() {
+        registry.apply_transfer(old_balance > 0 && balance == 0, old_balance == 0 && balance > 0, 0)?;
+    }
+    let kyc_ai = &ctx.accounts.user_kyc;
+    if kyc_ai.owner == &crate::ID {
+        let mut user_kyc = load_user_kyc(kyc_ai, &position.holder)?;
+        if user_kyc.record_position_change(old_balance, balance) {
+            store_user_kyc(&user_kyc, kyc_ai)?;
+        }
+    }
+    position.balance = balance;
+    emit!(EvtSyncHolderPosition {
+        mint: position.mint,
+        authority: ctx.accounts.authority.key(),
+        holder: position.holder,
+        old_balance,
+        new_balance: balance,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ProposeRecovery<'info> {
+    #[account(mut)]
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: wallet whose keys were lost
+    pub old_owner: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = officer,
+        space = 8 + RecoveryRequest::LEN,
+        seeds = [b"recovery", mint.key().as_ref(), old_owner.key().as_ref()],
+        bump
+    )]
+    pub recovery_request: Account<'info, RecoveryRequest>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_propose_recovery(ctx: Context<ProposeRecovery>, new_owner: Pubkey) -> Result<()> {
+    let old_owner = ctx.accounts.old_owner.key();
+    require!(new_owner != Pubkey::default() && new_owner != old_owner, CustomError::InvalidRecoveryWallet);
+    let request = &mut ctx.accounts.recovery_request;
+    request.mint = ctx.accounts.mint.key();
+    request.old_owner = old_owner;
+    request.new_owner = new_owner;
+    request.proposed_by = ctx.accounts.officer.key();
+    request.approved_by = None;
+    request.proposed_at = Clock::get()?.unix_timestamp;
+    request.bump = ctx.bumps.recovery_request;
+    request.expires_at = request.proposed_at.saturating_add(ctx.accounts.compliance_config.pending_change_ttl());
+    emit!(EvtProposeRecovery {
+        mint: request.mint,
+        old_owner,
+        new_owner,
+        officer: request.proposed_by,
+        expires_at: request.expires_at,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ApproveRecovery<'info> {
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    #[account(
+        mut,
+        seeds = [b"recovery", recovery_request.mint.as_ref(), recovery_request.old_owner.as_ref()],
+        bump = recovery_request.bump
+    )]
+    pub recovery_request: Account<'info, RecoveryRequest>,
+}
+
+pub fn handle_approve_recovery(ctx: Context<ApproveRecovery>) -> Result<()> {
+    let request = &mut ctx.accounts.recovery_request;
+    let officer = ctx.accounts.officer.key();
+    require!(request.approved_by.is_none(), CustomError::RecoveryAlreadyApproved);
+    require!(Clock::get()?.unix_timestamp < request.expires_at, CustomError::RecoveryExpired);
+    require_keys_neq!(request.proposed_by, officer, CustomError::RecoveryApprovalRequired);
+    request.approved_by = Some(officer);
+    emit!(EvtApproveRecovery { mint: request.mint, old_owner: request.old_owner, officer });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelRecovery<'info> {
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    #[account(
+        mut,
+        seeds = [b"recovery", recovery_request.mint.as_ref(), recovery_request.old_owner.as_ref()],
+        bump = recovery_request.bump,
+        close = proposer
+    )]
+    pub recovery_request: Account<'info, RecoveryRequest>,
+    /// CHECK: refunded the request rent
+    #[account(mut, address = recovery_request.proposed_by)]
+    pub proposer: UncheckedAccount<'info>,
+}
+
+pub fn handle_cancel_recovery(ctx: Context<CancelRecovery>) -> Result<()> {
+    let request = &ctx.accounts.recovery_request;
+    emit!(EvtCancelRecovery { mint: request.mint, old_owner: request.old_owner, officer: ctx.accounts.officer.key() });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ExecuteRecovery<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Transfer hook authority of the mint, lifts the hook for the recovery transfer and is refunded the
+    /// old KYC record's rent
+    #[account(mut)]
+    pub hook_authority: Signer<'info>,
+    #[account(mut, mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+    /// CHECK: program-controlled PermanentDelegate of the mint
+    #[account(seeds = [b"permanent-delegate", mint.key().as_ref()], bump)]
+    pub permanent_delegate: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"recovery", mint.key().as_ref(), recovery_request.old_owner.as_ref()],
+        bump = recovery_request.bump,
+        close = proposer
+    )]
+    pub recovery_request: Box<Account<'info, RecoveryRequest>>,
+    /// CHECK: refunded the request rent
+    #[account(mut, address = recovery_request.proposed_by)]
+    pub proposer: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = recovery_request.old_owner,
+        token::token_program = token_program
+    )]
+    pub old_token: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = recovery_request.new_owner,
+        token::token_program = token_program
+    )]
+    pub new_token: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Migrated and closed by the first recovery out of the lost wallet, absent for its other mints
+    #[account(mut, seeds = [b"user-kyc", recovery_request.old_owner.as_ref()], bump, close = hook_authority)]
+    pub old_kyc: Option<Box<Account<'info, UserKYC>>>,
+    /// Carries over the old record on the first recovery into this wallet
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + UserKYC::LEN,
+        seeds = [b"user-kyc", recovery_request.new_owner.as_ref()],
+        bump
+    )]
+    pub new_kyc: Box<Account<'info, UserKYC>>,
+    /// CHECK: mint policy PDA, decides which records move with the balance
+    #[account(seeds = [b"mint-policy", mint.key().as_ref()], bump)]
+    pub mint_policy: UncheckedAccount<'info>,
+    /// CHECK: holder registry PDA
+    #[account(mut, seeds = [b"holder-registry", mint.key().as_ref()], bump)]
+    pub holder_registry: UncheckedAccount<'info>,
+    /// CHECK: position PDA of the old wallet, required while the mint has a holder registry
+    #[account(mut, seeds = [b"holder-position", mint.key().as_ref(), recovery_request.old_owner.as_ref()], bump)]
+    pub old_position: UncheckedAccount<'info>,
+    /// CHECK: position PDA of the new wallet
+    #[account(mut, seeds = [b"holder-position", mint.key().as_ref(), recovery_request.new_owner.as_ref()], bump)]
+    pub new_position: UncheckedAccount<'info>,
+    /// CHECK: holding lots PDA of the old wallet, required while the policy has a holding period
+    #[account(mut, seeds = [b"holding-lots", mint.key().as_ref(), recovery_request.old_owner.as_ref()], bump)]
+    pub old_lots: UncheckedAccount<'info>,
+    /// CHECK: holding lots PDA of the new wallet
+    #[account(mut, seeds = [b"holding-lots", mint.key().as_ref(), recovery_request.new_owner.as_ref()], bump)]
+    pub new_lots: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Moves the lost wallet's balance to the new wallet through the program's PermanentDelegate. A hooked
+/// transfer would call back into this program, which Solana rejects as reentrancy, so the hook is lifted
+/// for this one transfer and its bookkeeping done here.
+pub fn handle_execute_recovery(ctx: Context<ExecuteRecovery>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let request = &ctx.accounts.recovery_request;
+    let approved_by = request.approval(now)?;
+    let mint_key = ctx.accounts.mint.key();
+    let mint_ai = ctx.accounts.mint.to_account_info();
+    require_transfer_hook_authority(&mint_ai, &ctx.accounts.hook_authority.key())?;
+    {
+        let data = mint_ai.try_borrow_data()?;
+        let mint = StateWithExtensions::<MintState>::unpack(&data)?;
+        let delegate = mint.get_extension::<PermanentDelegate>().map_err(|_| CustomError::InvalidPermanentDelegate)?;
+        require!(
+            Option::<Pubkey>::from(delegate.delegate) == Some(ctx.accounts.permanent_delegate.key()),
+            CustomError::InvalidPermanentDelegate
+        );
+    }
+
+    let amount = ctx.accounts.old_token.amount;
+    if amount > 0 {
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let hook_authority = ctx.accounts.hook_authority.to_account_info();
+        let set_hook = |program_id: Option<Pubkey>| {
+            invoke(
+                &transfer_hook_instruction::update(
+                    &token_program.key(),
+                    &mint_key,
+                    &hook_authority.key(),
+                    &[],
+                    program_id
+                )?,
+                &[mint_ai.clone(), hook_authority.clone()]
+            )
+        };
+        set_hook(None)?;
+        let seeds = &[b"permanent-delegate", mint_key.as_ref(), &[ctx.bumps.permanent_delegate]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                TransferChecked {
+                    from: ctx.accounts.old_token.to_account_info(),
+                    mint: mint_ai.clone(),
+                    to: ctx.accounts.new_token.to_account_info(),
+                    authority: ctx.accounts.permanent_delegate.to_account_info(),
+                },
+                &[&seeds[..]]
+            ),
+            amount,
+            ctx.accounts.mint.decimals
+        )?;
+        set_hook(Some(crate::ID))?;
+    }
+
+    match &ctx.accounts.old_kyc {
+        Some(old_kyc) if ctx.accounts.new_kyc.user == Pubkey::default() => {
+            let record = old_kyc.recovered_to(request.new_owner, now);
+            ctx.accounts.new_kyc.set_inner(record);
+        }
+        Some(_) => {}
+        None => require!(ctx.accounts.new_kyc.user != Pubkey::default(), CustomError::UserKycNotFound),
+    }
+
+    if let Some(mut registry) = load_if_initialized::<HolderRegistry>(&ctx.accounts.holder_registry)? {
+        let mint = &ctx.accounts.mint;
+        let mut old_position = load_position(&ctx.accounts.old_position, mint, &request.old_owner, amount)?;
+        let new_balance = ctx.accounts.new_token.amount;
+        let mut new_position = load_position(&ctx.accounts.new_position, mint, &request.new_owner, new_balance)?;
+        let new_before = new_position.balance;
+        // same beneficial owner under a new key, never blocked by the holder cap
+        let (exits, joins) = HolderPosition::move_balance(&mut old_position, &mut new_position, amount);
+        ctx.accounts.new_kyc.record_position_change(new_before, new_position.balance);
+        registry.apply_transfer(exits, joins, 0)?;
+        registry.try_serialize(&mut &mut ctx.accounts.holder_registry.try_borrow_mut_data()?[..])?;
+        old_position.try_serialize(&mut &mut ctx.accounts.old_position.try_borrow_mut_data()?[..])?;
+        new_position.try_serialize(&mut &mut ctx.accounts.new_position.try_borrow_mut_data()?[..])?;
+    }
+
+    // lots keep their acquisition time so a lockup cannot be shed through recovery
+    let policy = load_if_initialized::<MintPolicy>(&ctx.accounts.mint_policy)?;
+    if policy.is_some_and(|p| p.modules.iter().any(|m| matches!(m, ComplianceModule::HoldingPeriod { .. }))) {
+        let mut old_lots = load_if_initialized::<HoldingLots>(&ctx.accounts.old_lots)?
+            .ok_or(CustomError::HoldingLotsNotInitialized)?;
+        let mut new_lots = load_if_initialized::<HoldingLots>(&ctx.accounts.new_lots)?
+            .ok_or(CustomError::HoldingLotsNotInitialized)?;
+        new_lots.absorb(&old_lots.lots);
+        old_lots.lots.clear();
+        old_lots.try_serialize(&mut &mut ctx.accounts.old_lots.try_borrow_mut_data()?[..])?;
+        new_lots.try_serialize(&mut &mut ctx.accounts.new_lots.try_borrow_mut_data()?[..])?;
+    }
+
+
+    emit!(EvtExecuteRecovery {
+        mint: mint_key,
+        old_owner: request.old_owner,
+        new_owner: request.new_owner,
+        old_token: ctx.accounts.old_token.key(),
+        new_token: ctx.accounts.new_token.key(),
+        amount,
+        proposed_by: request.proposed_by,
+        approved_by,
+        hook_authority: ctx.accounts.hook_authority.key(),
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CheckTransfer<'info> {
+    pub mint: InterfaceAccount<'info, Mint>,
+    /// CHECK
+    pub source_owner: UncheckedAccount<'info>,
+    #[account(token::mint = mint, token::authority = source_owner)]
+    pub source_token: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK
+    pub destination_owner: UncheckedAccount<'info>,
+    /// Absent when the destination account does not exist yet
+    #[account(token::mint = mint, token::authority = destination_owner)]
+    pub destination_token: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: PDA user KYC of source owner
+    #[account(seeds = [b"user-kyc", source_owner.key().as_ref()], bump)]
+    pub source_kyc: UncheckedAccount<'info>,
+    /// CHECK: PDA user KYC of destination owner
+    #[account(seeds = [b"user-kyc", destination_owner.key().as_ref()], bump)]
+    pub destination_kyc: UncheckedAccount<'info>,
+    /// CHECK: exemption registry PDA
+    #[account(seeds = [b"exemption-registry"], bump)]
+    pub exemption_registry: UncheckedAccount<'info>,
+    /// CHECK: compliance config PDA
+    #[account(seeds = [b"compliance-config"], bump)]
+    pub compliance_config: UncheckedAccount<'info>,
+    /// CHECK: mint policy PDA
+    #[account(seeds = [b"mint-policy", mint.key().as_ref()], bump)]
+    pub mint_policy: UncheckedAccount<'info>,
+    /// CHECK: holding lots PDA of source owner
+    #[account(seeds = [b"holding-lots", mint.key().as_ref(), source_owner.key().as_ref()], bump)]
+    pub source_lots: UncheckedAccount<'info>,
+    /// CHECK: holding lots PDA of destination owner
+    #[account(seeds = [b"holding-lots", mint.key().as_ref(), destination_owner.key().as_ref()], bump)]
+    pub destination_lots: UncheckedAccount<'info>,
+    /// CHECK: holder registry PDA
+    #[account(seeds = [b"holder-registry", mint.key().as_ref()], bump)]
+    pub holder_registry: UncheckedAccount<'info>,
+    /// CHECK: mint-specific status of source owner
+    #[account(seeds = [b"holder-status", mint.key().as_ref(), source_owner.key().as_ref()], bump)]
+    pub source_status: UncheckedAccount<'info>,
+    /// CHECK: mint-specific status of destination owner
+    #[account(seeds = [b"holder-status", mint.key().as_ref(), destination_owner.key().as_ref()], bump)]
+    pub destination_status: UncheckedAccount<'info>,
+    /// CHECK: compliance approval for this pair of holders
+    #[account(
+        seeds = [
+            b"transfer-approval",
+            mint.key().as_ref(),
+            source_owner.key().as_ref(),
+            destination_owner.key().as_ref(),
+        ],
+        bump
+    )]
+    pub transfer_approval: UncheckedAccount<'info>,
+    /// CHECK: holiday calendar PDA, no holidays until initialized
+    #[account(seeds = [b"holiday-calendar", mint.key().as_ref()], bump)]
+    pub holiday_calendar: UncheckedAccount<'info>,
+    /// CHECK: travel rule record for this pair of holders
+    #[account(
+        seeds = [b"travel-rule", mint.key().as_ref(), source_owner.key().as_ref(), destination_owner.key().as_ref()],
+        bump
+    )]
+    pub travel_rule_record: UncheckedAccount<'info>,
+    /// CHECK: transfer journal PDA
+    #[account(seeds = [b"transfer-journal", mint.key().as_ref()], bump)]
+    pub transfer_journal: UncheckedAccount<'info>,
+    /// CHECK: approved delegate that would move the tokens, absent when the source owner signs
+    pub delegate: Option<UncheckedAccount<'info>>,
+    /// CHECK: PDA user KYC of delegate, matched to it when loaded
+    pub delegate_kyc: Option<UncheckedAccount<'info>>,
+    /// CHECK: position PDA of source owner
+    #[account(seeds = [b"holder-position", mint.key().as_ref(), source_owner.key().as_ref()], bump)]
+    pub source_position: UncheckedAccount<'info>,
+    /// CHECK: position PDA of destination owner
+    #[account(seeds = [b"holder-position", mint.key().as_ref(), destination_owner.key().as_ref()], bump)]
+    pub destination_position: UncheckedAccount<'info>,
+}
+
+/// Evaluates a transfer without moving tokens or writing state. Rule failures become the verdict,
+/// only malformed accounts fail the instruction. Identity-resolving mints take the identity accounts as
+/// remaining accounts, in hook order.
+pub fn handle_check_transfer<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CheckTransfer<'info>>,
+    amount: u64
+) -> Result<TransferVerdict> {
+    let a = &ctx.accounts;
+    let accounts = TransferAccounts {
+        mint: &a.mint,
+        source_owner: &a.source_owner,
+        destination_owner: &a.destination_owner,
+        source_kyc: &a.source_kyc,
+        destination_kyc: &a.destination_kyc,
+        exemption_registry: &a.exemption_registry,
+        compliance_config: &a.compliance_config,
+        mint_policy: &a.mint_policy,
+        source_lots: &a.source_lots,
+        destination_lots: &a.destination_lots,
+        holder_registry: &a.holder_registry,
+        source_status: &a.source_status,
+        destination_status: &a.destination_status,
+        transfer_approval: &a.transfer_approval,
+        holiday_calendar: &a.holiday_calendar,
+        travel_rule_record: &a.travel_rule_record,
+        transfer_journal: &a.transfer_journal,
+        source_position: &a.source_position,
+        destination_position: &a.destination_position,
+        identity_accounts: ctx.remaining_accounts,
+        delegate: match (&a.delegate, &a.delegate_kyc) {
+            (Some(authority), Some(kyc)) => Some(Delegate { authority, kyc }),
+            _ => None,
+        },
+        source_balance: a.source_token.amount,
+        destination_balance: a.destination_token.as_ref().map_or(0, |t| t.amount),
+    };
+    match evaluate_transfer(&accounts, amount, false) {
+        Ok(()) => Ok(TransferVerdict { allowed: true, reason: 0 }),
+        Err(Error::AnchorError(e)) => Ok(TransferVerdict { allowed: false, reason: e.error_code_number }),
+        Err(e) => Err(e),
+    }
+}
+
+#[derive(Accounts)]
+pub struct ApproveTransfer<'info> {
+    /// Pays the approval rent and is refunded it on revocation
+    #[account(mut)]
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    /// CHECK
+    pub source_owner: UncheckedAccount<'info>,
+    /// CHECK
+    pub destination_owner: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = officer,
+        space = 8 + TransferApproval::LEN,
+        seeds = [
+            b"transfer-approval",
+            mint.key().as_ref(),
+            source_owner.key().as_ref(),
+            destination_owner.key().as_ref(),
+        ],
+        bump
+    )]
+    pub transfer_approval: Account<'info, TransferApproval>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Replaces any earlier approval for the pair, `expires_at` must fall within `TransferApproval::MAX_TTL`
+pub fn handle_approve_transfer(ctx: Context<ApproveTransfer>, amount: u64, expires_at: i64) -> Result<()> {
+    TransferApproval::validate_expiry(expires_at, Clock::get()?.unix_timestamp)?;
+    let approval = &mut ctx.accounts.transfer_approval;
+    approval.mint = ctx.accounts.mint.key();
+    approval.source_owner = ctx.accounts.source_owner.key();
+    approval.destination_owner = ctx.accounts.destination_owner.key();
+    approval.remaining_amount = amount;
+    approval.expires_at = expires_at;
+    approval.approved_by = ctx.accounts.officer.key();
+    approval.bump = ctx.bumps.transfer_approval;
+    emit!(EvtApproveTransfer {
+        mint: approval.mint,
+        source_owner: approval.source_owner,
+        destination_owner: approval.destination_owner,
+        officer: approval.approved_by,
+        amount,
+        expires_at,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RevokeTransferApproval<'info> {
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    #[account(
+        mut,
+        seeds = [
+            b"transfer-approval",
+            transfer_approval.mint.as_ref(),
+            transfer_approval.source_owner.as_ref(),
+            transfer_approval.destination_owner.as_ref(),
+        ],
+        bump = transfer_approval.bump,
+        close = approver
+    )]
+    pub transfer_approval: Account<'info, TransferApproval>,
+    /// CHECK: refunded the approval rent
+    #[account(mut, address = transfer_approval.approved_by)]
+    pub approver: UncheckedAccount<'info>,
+}
+
+pub fn handle_revoke_transfer_approval(ctx: Context<RevokeTransferApproval>) -> Result<()> {
+    let approval = &ctx.accounts.transfer_approval;
+    emit!(EvtRevokeTransferApproval {
+        mint: approval.mint,
+        source_owner: approval.source_owner,
+        destination_owner: approval.destination_owner,
+        officer: ctx.accounts.officer.key(),
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetHolidayCalendar<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"mint-policy", mint_policy.mint.as_ref()],
+        bump = mint_policy.bump,
+        has_one = authority @ CustomError::Unauthorized
+    )]
+    pub mint_policy: Account<'info, MintPolicy>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + HolidayCalendar::LEN,
+        seeds = [b"holiday-calendar", mint_policy.mint.as_ref()],
+        bump
+    )]
+    pub holiday_calendar: Account<'info, HolidayCalendar>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Replaces the calendar, `days` are local day numbers since the epoch in the sessions' offset
+pub fn handle_set_holiday_calendar(ctx: Context<SetHolidayCalendar>, days: Vec<i64>) -> Result<()> {
+    require!(days.len() <= HolidayCalendar::MAX_DAYS, CustomError::TooManyHolidays);
+    let calendar = &mut ctx.accounts.holiday_calendar;
+    calendar.mint = ctx.accounts.mint_policy.mint;
+    calendar.days = days;
+    calendar.bump = ctx.bumps.holiday_calendar;
+    emit!(EvtSetHolidayCalendar {
+        mint: calendar.mint,
+        authority: ctx.accounts.authority.key(),
+        days: calendar.days.clone(),
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RecordTravelRule<'info> {
+    #[account(mut)]
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    /// CHECK
+    pub source_owner: UncheckedAccount<'info>,
+    /// CHECK
+    pub destination_owner: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = officer,
+        space = 8 + TravelRuleRecord::LEN,
+        seeds = [b"travel-rule", mint.key().as_ref(), source_owner.key().as_ref(), destination_owner.key().as_ref()],
+        bump
+    )]
+    pub travel_rule_record: Account<'info, TravelRuleRecord>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Writes the next record for the pair once the earlier one is consumed or cancelled
+pub fn handle_record_travel_rule(
+    ctx: Context<RecordTravelRule>,
+    nonce: u64,
+    amount: u64,
+    originator_hash: [u8; 32],
+    beneficiary_hash: [u8; 32]
+) -> Result<()> {
+    let record = &mut ctx.accounts.travel_rule_record;
+    record.check_replaceable(nonce)?;
+    record.set_inner(TravelRuleRecord {
+        mint: ctx.accounts.mint.key(),
+        source_owner: ctx.accounts.source_owner.key(),
+        destination_owner: ctx.accounts.destination_owner.key(),
+        nonce,
+        amount,
+        originator_hash,
+        beneficiary_hash,
+        recorded_by: ctx.accounts.officer.key(),
+        recorded_at: Clock::get()?.unix_timestamp,
+        consumed: false,
+        bump: ctx.bumps.travel_rule_record,
+    });
+    emit!(EvtRecordTravelRule {
+        mint: record.mint,
+        source_owner: record.source_owner,
+        destination_owner: record.destination_owner,
+        nonce,
+        amount,
+        originator_hash,
+        beneficiary_hash,
+        officer: record.recorded_by,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelTravelRule<'info> {
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    #[account(
+        mut,
+        seeds = [
+            b"travel-rule",
+            travel_rule_record.mint.as_ref(),
+            travel_rule_record.source_owner.as_ref(),
+            travel_rule_record.destination_owner.as_ref(),
+        ],
+        bump = travel_rule_record.bump
+    )]
+    pub travel_rule_record: Account<'info, TravelRuleRecord>,
+}
+
+/// Withdraws a pending record filed in error, freeing the slot for the next nonce
+pub fn handle_cancel_travel_rule(ctx: Context<CancelTravelRule>) -> Result<()> {
+    let record = &mut ctx.accounts.travel_rule_record;
+    require!(!record.consumed, CustomError::TravelRuleRecordRequired);
+    record.consumed = true;
+    emit!(EvtCancelTravelRule {
+        mint: record.mint,
+        source_owner: record.source_owner,
+        destination_owner: record.destination_owner,
+        nonce: record.nonce,
+        officer: ctx.accounts.officer.key(),
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeTransferJournal<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"mint-policy", mint_policy.mint.as_ref()],
+        bump = mint_policy.bump,
+        has_one = authority @ CustomError::Unauthorized
+    )]
+    pub mint_policy: Account<'info, MintPolicy>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + TransferJournal::LEN,
+        seeds = [b"transfer-journal", mint_policy.mint.as_ref()],
+        bump
+    )]
+    pub transfer_journal: AccountLoader<'info, TransferJournal>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_initialize_transfer_journal(ctx: Context<InitializeTransferJournal>) -> Result<()> {
+    let mut journal = ctx.accounts.transfer_journal.load_init()?;
+    journal.mint = ctx.accounts.mint_policy.mint;
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(page: u64)]
+pub struct ArchiveJournalPage<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, seeds = [b"transfer-journal", transfer_journal.load()?.mint.as_ref()], bump)]
+    pub transfer_journal: AccountLoader<'info, TransferJournal>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + JournalArchive::LEN,
+        seeds = [b"journal-archive", transfer_journal.load()?.mint.as_ref(), &page.to_le_bytes()],
+        bump
+    )]
+    pub journal_archive: AccountLoader<'info, JournalArchive>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Permissionless, anyone may pay to free the oldest full page of the ring
+pub fn handle_archive_journal_page(ctx: Context<ArchiveJournalPage>, page: u64) -> Result<()> {
+    let mut journal = ctx.accounts.transfer_journal.load_mut()?;
+    require_eq!(page, journal.next_page(), CustomError::TransferJournalPageNotFull);
+    let entries = journal.archive_page()?;
+    let mut archive = ctx.accounts.journal_archive.load_init()?;
+    archive.mint = journal.mint;
+    archive.page = page;
+    archive.entries = entries;
+    emit!(EvtArchiveJournalPage { mint: journal.mint, page, archive: ctx.accounts.journal_archive.key() });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetDualControl<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        has_one = authority @ CustomError::Unauthorized
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+}
+
+pub fn handle_set_dual_control(
+    ctx: Context<SetDualControl>,
+    dual_control_level: u8,
+    pending_change_ttl: i64
+) -> Result<()> {
+    require!(dual_control_level <= UserKYC::INSTITUTIONAL, CustomError::InvalidKycLevel);
+    require!(pending_change_ttl >= 0, CustomError::InvalidValidityPeriod);
+    let config = &mut ctx.accounts.compliance_config;
+    config.dual_control_level = dual_control_level;
+    config.pending_change_ttl = pending_change_ttl;
+    emit!(EvtSetDualControl { authority: config.authority, dual_control_level, pending_change_ttl });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ProposeKycChange<'info> {
+    #[account(mut)]
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    /// CHECK
+    pub user: UncheckedAccount<'info>,
+    #[account(seeds = [b"user-kyc", user.key().as_ref()], bump)]
+    pub user_kyc: Account<'info, UserKYC>,
+    #[account(
+        init,
+        payer = officer,
+        space = 8 + PendingKycChange::LEN,
+        seeds = [b"kyc-change", user.key().as_ref()],
+        bump
+    )]
+    pub pending_change: Account<'info, PendingKycChange>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_propose_kyc_change(
+    ctx: Context<ProposeKycChange>,
+    kyc_level: Option<u8>,
+    flags_to_set: u8,
+    flags_to_clear: u8
+) -> Result<()> {
+    require!(!matches!(kyc_level, Some(level) if level > UserKYC::INSTITUTIONAL), CustomError::InvalidKycLevel);
+    require!(kyc_level.is_some() || flags_to_set != 0 || flags_to_clear != 0, CustomError::EmptyPendingChange);
+    let now = Clock::get()?.unix_timestamp;
+    let change = &mut ctx.accounts.pending_change;
+    change.set_inner(PendingKycChange {
+        user: ctx.accounts.user.key(),
+        kyc_level,
+        flags_to_set,
+        flags_to_clear,
+        proposed_by: ctx.accounts.officer.key(),
+        proposed_at: now,
+        expires_at: now.saturating_add(ctx.accounts.compliance_config.pending_change_ttl()),
+        bump: ctx.bumps.pending_change,
+    });
+    emit!(EvtProposeKycChange {
+        user: change.user,
+        officer: change.proposed_by,
+        kyc_level,
+        flags_to_set,
+        flags_to_clear,
+        expires_at: change.expires_at,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ApproveKycChange<'info> {
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    #[account(mut, seeds = [b"user-kyc", pending_change.user.as_ref()], bump)]
+    pub user_kyc: Account<'info, UserKYC>,
+    #[account(
+        mut,
+        seeds = [b"kyc-change", pending_change.user.as_ref()],
+        bump = pending_change.bump,
+        close = proposer
+    )]
+    pub pending_change: Account<'info, PendingKycChange>,
+    /// CHECK: refunded the pending change rent
+    #[account(mut, address = pending_change.proposed_by)]
+    pub proposer: UncheckedAccount<'info>,
+}
+
+/// Approves and applies the change in one step
+pub fn handle_approve_kyc_change(ctx: Context<ApproveKycChange>) -> Result<()> {
+    let change = &ctx.accounts.pending_change;
+    let officer = ctx.accounts.officer.key();
+    let now = Clock::get()?.unix_timestamp;
+    require_keys_neq!(change.proposed_by, officer, CustomError::SameOfficerApproval);
+    require!(now < change.expires_at, CustomError::PendingChangeExpired);
+    let user_kyc = &mut ctx.accounts.user_kyc;
+    change.apply(user_kyc, &ctx.accounts.compliance_config, now);
+    emit!(EvtApproveKycChange {
+        user: change.user,
+        proposed_by: change.proposed_by,
+        approved_by: officer,
+        kyc_level: user_kyc.kyc_level,
+        flags: user_kyc.flags,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelKycChange<'info> {
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    #[account(
+        mut,
+        seeds = [b"kyc-change", pending_change.user.as_ref()],
+        bump = pending_change.bump,
+        close = proposer
+    )]
+    pub pending_change: Account<'info, PendingKycChange>,
+    /// CHECK: refunded the pending change rent
+    #[account(mut, address = pending_change.proposed_by)]
+    pub proposer: UncheckedAccount<'info>,
+}
+
+/// Withdraws a proposal, also how expired ones are cleared away
+pub fn handle_cancel_kyc_change(ctx: Context<CancelKycChange>) -> Result<()> {
+    let change = &ctx.accounts.pending_change;
+    emit!(EvtCancelKycChange {
+        user: change.user,
+        proposed_by: change.proposed_by,
+        officer: ctx.accounts.officer.key(),
+    });
+    Ok(())
+}
+
+/// Compute kept back for each batch entry, entries past the budget are skipped instead of failing the batch
+const BATCH_ENTRY_COMPUTE: u64 = 25_000;
+
+#[derive(Accounts)]
+pub struct BatchKyc<'info> {
+    /// Pays for created records
+    #[account(mut)]
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Runs `f` for each entry and its remaining account. Rule failures are reported per entry, only
+/// errors outside the program's own codes abort the batch. `remaining_compute` is
+/// `sol_remaining_compute_units` on chain.
+pub(crate) fn run_batch<'info, T>(
+    entries: &[T],
+    accounts: &[AccountInfo<'info>],
+    remaining_compute: impl Fn() -> u64,
+    user: impl Fn(&T) -> Pubkey,
+    mut f: impl FnMut(&T, &AccountInfo<'info>) -> Result<()>
+) -> Result<Vec<BatchKycResult>> {
+    require!(entries.len() <= BatchKycResult::MAX_ENTRIES, CustomError::TooManyBatchEntries);
+    require!(accounts.len() == entries.len(), CustomError::InvalidBatchAccount);
+    entries
+        .iter()
+        .zip(accounts)
+        .map(|(entry, ai)| {
+            let result = if remaining_compute() < BATCH_ENTRY_COMPUTE {
+                Err(CustomError::BatchComputeExhausted.into())
+            } else {
+                f(entry, ai)
+            };
+            let error_code = match result {
+                Ok(()) => 0,
+                Err(Error::AnchorError(e)) => e.error_code_number,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+            Ok(BatchKycResult { user: user(entry), error_code })
+        })
+        .collect()
+}
+
+/// Creates one record per entry; remaining accounts are the entries' `user-kyc` PDAs, writable
+pub fn handle_batch_initialize_user_kyc<'info>(
+    ctx: Context<'_, '_, 'info, 'info, BatchKyc<'info>>,
+    entries: Vec<BatchKycEntry>
+) -> Result<Vec<BatchKycResult>> {
+    let now = Clock::get()?.unix_timestamp;
+    let accounts = &ctx.accounts;
+    let space = 8 + UserKYC::LEN;
+    run_batch(
+        &entries,
+        ctx.remaining_accounts,
+        sol_remaining_compute_units,
+        |entry| entry.user,
+        |entry, ai| {
+            let (address, bump) = Pubkey::find_program_address(&[b"user-kyc", entry.user.as_ref()], &crate::ID);
+            require_keys_eq!(ai.key(), address, CustomError::InvalidBatchAccount);
+            require!(
+                ai.owner == &anchor_lang::system_program::ID && ai.data_is_empty(),
+                CustomError::UserKycAlreadyExists
+            );
+            let mut user_kyc = UserKYC::default();
+            let (country, state, city) = (&entry.country, &entry.state, &entry.city);
+            let config = &accounts.compliance_config;
+            fill_new_user_kyc(&mut user_kyc, entry.user, entry.kyc_level, country, state, city, now, config)?;
+            let seeds: &[&[u8]] = &[b"user-kyc", entry.user.as_ref(), &[bump]];
+            create_pda(ai, &accounts.officer, &accounts.system_program, space, seeds)?;
+            user_kyc.try_serialize(&mut &mut ai.try_borrow_mut_data()?[..])
+        }
+    )
+}
+
+/// Applies one change per entry, e.g. sanctioning a list of wallets; remaining accounts are the
+/// entries' `user-kyc` PDAs, writable
+pub fn handle_batch_update_user_kyc<'info>(
+    ctx: Context<'_, '_, 'info, 'info, BatchKyc<'info>>,
+    updates: Vec<BatchKycUpdate>
+) -> Result<Vec<BatchKycResult>> {
+    let now = Clock::get()?.unix_timestamp;
+    let config = &ctx.accounts.compliance_config;
+    run_batch(
+        &updates,
+        ctx.remaining_accounts,
+        sol_remaining_compute_units,
+        |update| update.user,
+        |update, ai| {
+            let address = Pubkey::find_program_address(&[b"user-kyc", update.user.as_ref()], &crate::ID).0;
+            require_keys_eq!(ai.key(), address, CustomError::InvalidBatchAccount);
+            let mut user_kyc = load_user_kyc(ai, &update.user)?;
+            require!(
+                !config.requires_dual_control(&user_kyc, update.kyc_level, update.flags_to_clear),
+                CustomError::DualControlRequired
+            );
+            if let Some(level) = update.kyc_level {
+                require!(level <= UserKYC::INSTITUTIONAL, CustomError::InvalidKycLevel);
+                if level != user_kyc.kyc_level {
+                    user_kyc.verify(now, config.validity_period(level));
+                }
+                user_kyc.kyc_level = level;
+            }
+            if let Some(score) = update.risk_score {
+                require!(score <= UserKYC::MAX_RISK_SCORE, CustomError::InvalidRiskScore);
+                user_kyc.risk_score = score;
+            }
+            user_kyc.flags = (user_kyc.flags | update.flags_to_set) & !update.flags_to_clear;
+            user_kyc.last_updated = now;
+            store_user_kyc(&user_kyc, ai)
+        }
+    )
+}
+
+#[derive(Accounts)]
+pub struct CloseUserKyc<'info> {
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    /// CHECK
+    pub user: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"user-kyc", user.key().as_ref()], bump, close = refund_to)]
+    pub user_kyc: Account<'info, UserKYC>,
+    /// CHECK: the user's `IdentityLinks`, may not exist
+    #[account(seeds = [b"identity-links", user.key().as_ref()], bump)]
+    pub identity_links: UncheckedAccount<'info>,
+    /// CHECK: receives the record rent
+    #[account(mut)]
+    pub refund_to: UncheckedAccount<'info>,
+}
+
+/// Deletes a record once none of its holder positions has a balance and no wallet is linked to it
+pub fn handle_close_user_kyc(ctx: Context<CloseUserKyc>) -> Result<()> {
+    // restrictions only come off through dual control, closing and recreating the record would skip it
+    require!(
+        (ctx.accounts.user_kyc.flags & UserKYC::DUAL_CONTROL_FLAGS) == 0,
+        CustomError::KycRecordRestricted
+    );
+    let links = load_if_initialized::<IdentityLinks>(&ctx.accounts.identity_links)?;
+    require!(links.map_or(0, |l| l.linked_wallets) == 0, CustomError::IdentityHasLinkedWallets);
+    require!(ctx.accounts.user_kyc.open_positions == 0, CustomError::UserHoldsPolicyTokens);
+    let user = ctx.accounts.user.key();
+
+    let record_hash = hash(&ctx.accounts.user_kyc.to_account_info().try_borrow_data()?).to_bytes();
+    emit!(EvtCloseUserKyc {
+        officer: ctx.accounts.officer.key(),
+        user,
+        record_hash,
+        refund_to: ctx.accounts.refund_to.key(),
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LinkWallet<'info> {
+    /// Pays the link rent and is refunded it on unlinking
+    #[account(mut)]
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    /// CHECK: any wallet, multisig or program vault, it does not sign
+    pub wallet: UncheckedAccount<'info>,
+    /// CHECK: the wallet's own `UserKYC`, may not exist
+    #[account(seeds = [b"user-kyc", wallet.key().as_ref()], bump)]
+    pub wallet_kyc: UncheckedAccount<'info>,
+    /// CHECK: key of the identity's `UserKYC`, which may be created later or never for exempt wallets
+    pub identity: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = officer,
+        space = 8 + WalletLink::LEN,
+        seeds = [b"wallet-link", wallet.key().as_ref()],
+        bump
+    )]
+    pub wallet_link: Account<'info, WalletLink>,
+    #[account(
+        init_if_needed,
+        payer = officer,
+        space = 8 + IdentityLinks::LEN,
+        seeds = [b"identity-links", identity.key().as_ref()],
+        bump
+    )]
+    pub identity_links: Account<'info, IdentityLinks>,
+    pub system_program: Program<'info, System>,
+}
+
+/// A wallet links to one identity at a time, unlink it first to move it. A wallet under a restriction
+/// only dual control can lift is not linked, one officer could otherwise trade it through a clean identity.
+pub fn handle_link_wallet(ctx: Context<LinkWallet>) -> Result<()> {
+    if ctx.accounts.wallet_kyc.owner == &crate::ID {
+        let wallet_kyc = load_user_kyc(&ctx.accounts.wallet_kyc, ctx.accounts.wallet.key)?;
+        require!((wallet_kyc.flags & UserKYC::DUAL_CONTROL_FLAGS) == 0, CustomError::KycRecordRestricted);
+    }
+    let links = &mut ctx.accounts.identity_links;
+    links.identity = ctx.accounts.identity.key();
+    links.linked_wallets = links.linked_wallets.saturating_add(1);
+    links.bump = ctx.bumps.identity_links;
+    let link = &mut ctx.accounts.wallet_link;
+    link.identity = ctx.accounts.identity.key();
+    link.wallet = ctx.accounts.wallet.key();
+    link.linked_by = ctx.accounts.officer.key();
+    link.linked_at = Clock::get()?.unix_timestamp;
+    link.bump = ctx.bumps.wallet_link;
+    emit!(EvtLinkWallet { officer: link.linked_by, wallet: link.wallet, identity: link.identity });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UnlinkWallet<'info> {
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"compliance-config"],
+        bump = compliance_config.bump,
+        constraint = compliance_config.is_officer(&officer.key()) @ CustomError::NotKycOfficer
+    )]
+    pub compliance_config: Account<'info, ComplianceConfig>,
+    #[account(
+        mut,
+        seeds = [b"wallet-link", wallet_link.wallet.as_ref()],
+        bump = wallet_link.bump,
+        close = linker
+    )]
+    pub wallet_link: Account<'info, WalletLink>,
+    #[account(mut, seeds = [b"identity-links", wallet_link.identity.as_ref()], bump = identity_links.bump)]
+    pub identity_links: Account<'info, IdentityLinks>,
+    /// CHECK: refunded the link rent
+    #[account(mut, address = wallet_link.linked_by)]
+    pub linker: UncheckedAccount<'info>,
+}
+
+/// The wallet can no longer transfer identity-resolving mints until it is linked again
+pub fn handle_unlink_wallet(ctx: Context<UnlinkWallet>) -> Result<()> {
+    let links = &mut ctx.accounts.identity_links;
+    links.linked_wallets = links.linked_wallets.saturating_sub(1);
+    let link = &ctx.accounts.wallet_link;
+    emit!(EvtUnlinkWallet { officer: ctx.accounts.officer.key(), wallet: link.wallet, identity: link.identity });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetComplianceMetadata<'info> {
+    /// Funds the metadata growing
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Update authority of the mint's token metadata
+    pub update_authority: Signer<'info>,
+    #[account(mut, mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Writes one compliance field into the metadata the mint stores in itself, validated and in the form the
+/// hook parses
+pub fn handle_set_compliance_metadata(ctx: Context<SetComplianceMetadata>, field: ComplianceMetadata) -> Result<()> {
+    field.validate()?;
+    let a = &ctx.accounts;
+    let mint_ai = a.mint.to_account_info();
+    let mut metadata = Token2022MetadataParser::parse_metadata_from_mint(&mint_ai.try_borrow_data()?)
+        .map_err(|_| CustomError::MintMetadataNotFound)?;
+    for (key, value) in field.fields() {
+        match value {
+            Some(value) => {
+                let old_len = metadata.tlv_size_of()?;
+                metadata.update(Field::Key(key.to_string()), value.clone());
+                let size = mint_ai.data_len() - old_len + metadata.tlv_size_of()?;
+                top_up_rent(&mint_ai, &a.payer, &a.system_program, size)?;
+                token_interface::token_metadata_update_field(
+                    CpiContext::new(a.token_program.to_account_info(), TokenMetadataUpdateField {
+                        program_id: a.token_program.to_account_info(),
+                        metadata: mint_ai.clone(),
+                        update_authority: a.update_authority.to_account_info(),
+                    }),
+                    Field::Key(key.to_string()),
+                    value
+                )?;
+            }
+            None => {
+                if metadata.remove_key(key) {
+                    let update_authority = a.update_authority.key;
+                    let ix = remove_key(a.token_program.key, mint_ai.key, update_authority, key.to_string(), false);
+                    invoke(&ix, &[mint_ai.clone(), a.update_authority.to_account_info()])?;
+                }
+            }
+        }
+    }
+    emit!(EvtSetComplianceMetadata { mint: a.mint.key(), update_authority: a.update_authority.key(), field });
+    Ok(())
+}
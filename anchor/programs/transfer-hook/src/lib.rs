@@ -6,8 +6,12 @@ pub mod state;
 pub use state::*;
 pub mod error;
 pub use error::*;
+pub mod event;
+pub use event::*;
 pub mod instructions;
 pub use instructions::*;
+pub mod iso3166;
+pub mod tests;
 // Set to your deployed hook program ID
 declare_id!("Hos5X6SbGqyDb8FfvRgiDqWpTE9C6FcgAkXrTeryUXwB");
 
@@ -16,8 +20,15 @@ pub mod transfer_hook {
     use super::*;
 
     #[instruction(discriminator = ExecuteInstruction::SPL_DISCRIMINATOR_SLICE)]
-    pub fn transfer_hook(ctx: Context<TransferHook>) -> Result<()> {
-        handle_transfer_hook(ctx)
+    pub fn transfer_hook<'info>(ctx: Context<'_, '_, 'info, 'info, TransferHook<'info>>, amount: u64) -> Result<()> {
+        handle_transfer_hook(ctx, amount)
+    }
+
+    pub fn check_transfer<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CheckTransfer<'info>>,
+        amount: u64
+    ) -> Result<TransferVerdict> {
+        handle_check_transfer(ctx, amount)
     }
 
     pub fn initialize_extra_account_meta_list(ctx: Context<InitializeExtraAccountMetaList>) -> Result<()> {
@@ -28,6 +39,38 @@ pub mod transfer_hook {
         handle_update_extra_account_meta_list(ctx)
     }
 
+    pub fn initialize_compliance_config(ctx: Context<InitializeComplianceConfig>) -> Result<()> {
+        handle_initialize_compliance_config(ctx)
+    }
+
+    pub fn update_compliance_authority(ctx: Context<ManageComplianceConfig>, new_authority: Pubkey) -> Result<()> {
+        handle_update_compliance_authority(ctx, new_authority)
+    }
+
+    pub fn add_kyc_officer(ctx: Context<ManageComplianceConfig>, officer: Pubkey) -> Result<()> {
+        handle_add_kyc_officer(ctx, officer)
+    }
+
+    pub fn remove_kyc_officer(ctx: Context<ManageComplianceConfig>, officer: Pubkey) -> Result<()> {
+        handle_remove_kyc_officer(ctx, officer)
+    }
+
+    pub fn set_kyc_validity(
+        ctx: Context<ManageComplianceConfig>,
+        kyc_validity: [i64; 4],
+        grace_period: i64
+    ) -> Result<()> {
+        handle_set_kyc_validity(ctx, kyc_validity, grace_period)
+    }
+
+    pub fn add_exemption(ctx: Context<ManageExemption>, key: Pubkey, kind: u8) -> Result<()> {
+        handle_add_exemption(ctx, key, kind)
+    }
+
+    pub fn remove_exemption(ctx: Context<ManageExemption>, key: Pubkey) -> Result<()> {
+        handle_remove_exemption(ctx, key)
+    }
+
     pub fn initialize_user_kyc(
         ctx: Context<InitializeUserKyc>,
         kyc_level: u8,
@@ -38,25 +81,206 @@ pub mod transfer_hook {
         handle_initialize_user_kyc(ctx, kyc_level, country, state, city)
     }
 
-    pub fn update_user_kyc(
-        ctx: Context<UpdateUserKyc>,
-        new_kyc_level: Option<u8>,
-        new_risk_score: Option<u8>,
-        flags_to_set: Option<u8>,
-        flags_to_clear: Option<u8>,
-        new_country: Option<String>,
-        new_state: Option<String>,
-        new_city: Option<String>
+    pub fn update_user_kyc(ctx: Context<UpdateUserKyc>, update: UserKycUpdate) -> Result<()> {
+        handle_update_user_kyc(ctx, update)
+    }
+
+    pub fn renew_kyc(ctx: Context<RenewKyc>, new_kyc_level: Option<u8>) -> Result<()> {
+        handle_renew_kyc(ctx, new_kyc_level)
+    }
+
+    pub fn initialize_kyc_provider_registry(ctx: Context<InitializeKycProviderRegistry>) -> Result<()> {
+        handle_initialize_kyc_provider_registry(ctx)
+    }
+
+    pub fn add_kyc_provider(ctx: Context<ManageKycProvider>, signing_key: Pubkey, max_kyc_level: u8) -> Result<()> {
+        handle_add_kyc_provider(ctx, signing_key, max_kyc_level)
+    }
+
+    pub fn remove_kyc_provider(ctx: Context<ManageKycProvider>, signing_key: Pubkey) -> Result<()> {
+        handle_remove_kyc_provider(ctx, signing_key)
+    }
+
+    pub fn submit_kyc_attestation(ctx: Context<SubmitKycAttestation>, attestation: KycAttestation) -> Result<()> {
+        handle_submit_kyc_attestation(ctx, attestation)
+    }
+
+    pub fn initialize_private_user_kyc(
+        ctx: Context<InitializePrivateUserKyc>,
+        kyc_level: u8,
+        identity_hash: [u8; 32],
+        country_group_mask: u32
+    ) -> Result<()> {
+        handle_initialize_private_user_kyc(ctx, kyc_level, identity_hash, country_group_mask)
+    }
+
+    pub fn migrate_user_kyc(ctx: Context<MigrateUserKyc>) -> Result<()> {
+        handle_migrate_user_kyc(ctx)
+    }
+
+    pub fn privatize_user_kyc(
+        ctx: Context<PrivatizeUserKyc>,
+        identity_hash: [u8; 32],
+        country_group_mask: Option<u32>
+    ) -> Result<()> {
+        handle_privatize_user_kyc(ctx, identity_hash, country_group_mask)
+    }
+
+    pub fn initialize_mint_policy(ctx: Context<InitializeMintPolicy>) -> Result<()> {
+        handle_initialize_mint_policy(ctx)
+    }
+
+    pub fn set_compliance_modules(ctx: Context<SetComplianceModules>, modules: Vec<ComplianceModule>) -> Result<()> {
+        handle_set_compliance_modules(ctx, modules)
+    }
+
+    pub fn set_lockup_periods(ctx: Context<ManageMintPolicy>, lockup_periods: [i64; 4]) -> Result<()> {
+        handle_set_lockup_periods(ctx, lockup_periods)
+    }
+
+    pub fn set_whitelist_required(ctx: Context<ManageMintPolicy>, whitelist_required: bool) -> Result<()> {
+        handle_set_whitelist_required(ctx, whitelist_required)
+    }
+
+    pub fn set_holder_status(ctx: Context<SetHolderStatus>, flags: u8, investor_class: Option<u8>) -> Result<()> {
+        handle_set_holder_status(ctx, flags, investor_class)
+    }
+
+    pub fn initialize_holding_lots(ctx: Context<InitializeHoldingLots>) -> Result<()> {
+        handle_initialize_holding_lots(ctx)
+    }
+
+    pub fn set_investor_class(ctx: Context<SetInvestorClass>, investor_class: u8) -> Result<()> {
+        handle_set_investor_class(ctx, investor_class)
+    }
+
+    pub fn set_investor_flags(ctx: Context<SetInvestorClass>, investor_flags: u8) -> Result<()> {
+        handle_set_investor_flags(ctx, investor_flags)
+    }
+
+    pub fn initialize_holder_registry(
+        ctx: Context<InitializeHolderRegistry>,
+        holder_count: u32,
+        max_holders: u32,
+        max_holder_bps: u16
     ) -> Result<()> {
-        handle_update_user_kyc(
-            ctx,
-            new_kyc_level,
-            new_risk_score,
-            flags_to_set,
-            flags_to_clear,
-            new_country,
-            new_state,
-            new_city
-        )
+        handle_initialize_holder_registry(ctx, holder_count, max_holders, max_holder_bps)
+    }
+
+    pub fn set_holder_limits(ctx: Context<ManageHolderRegistry>, max_holders: u32, max_holder_bps: u16) -> Result<()> {
+        handle_set_holder_limits(ctx, max_holders, max_holder_bps)
+    }
+
+    pub fn initialize_holder_position(ctx: Context<InitializeHolderPosition>) -> Result<()> {
+        handle_initialize_holder_position(ctx)
+    }
+
+    pub fn sync_holder_position(ctx: Context<SyncHolderPosition>, balance: u64) -> Result<()> {
+        handle_sync_holder_position(ctx, balance)
+    }
+
+    pub fn propose_recovery(ctx: Context<ProposeRecovery>, new_owner: Pubkey) -> Result<()> {
+        handle_propose_recovery(ctx, new_owner)
+    }
+
+    pub fn approve_recovery(ctx: Context<ApproveRecovery>) -> Result<()> {
+        handle_approve_recovery(ctx)
+    }
+
+    pub fn cancel_recovery(ctx: Context<CancelRecovery>) -> Result<()> {
+        handle_cancel_recovery(ctx)
+    }
+
+    pub fn execute_recovery(ctx: Context<ExecuteRecovery>) -> Result<()> {
+        handle_execute_recovery(ctx)
+    }
+
+    pub fn approve_transfer(ctx: Context<ApproveTransfer>, amount: u64, expires_at: i64) -> Result<()> {
+        handle_approve_transfer(ctx, amount, expires_at)
+    }
+
+    pub fn revoke_transfer_approval(ctx: Context<RevokeTransferApproval>) -> Result<()> {
+        handle_revoke_transfer_approval(ctx)
+    }
+
+    pub fn set_holiday_calendar(ctx: Context<SetHolidayCalendar>, days: Vec<i64>) -> Result<()> {
+        handle_set_holiday_calendar(ctx, days)
+    }
+
+    pub fn record_travel_rule(
+        ctx: Context<RecordTravelRule>,
+        nonce: u64,
+        amount: u64,
+        originator_hash: [u8; 32],
+        beneficiary_hash: [u8; 32]
+    ) -> Result<()> {
+        handle_record_travel_rule(ctx, nonce, amount, originator_hash, beneficiary_hash)
+    }
+
+    pub fn cancel_travel_rule(ctx: Context<CancelTravelRule>) -> Result<()> {
+        handle_cancel_travel_rule(ctx)
+    }
+
+    pub fn initialize_transfer_journal(ctx: Context<InitializeTransferJournal>) -> Result<()> {
+        handle_initialize_transfer_journal(ctx)
+    }
+
+    pub fn archive_journal_page(ctx: Context<ArchiveJournalPage>, page: u64) -> Result<()> {
+        handle_archive_journal_page(ctx, page)
+    }
+
+    pub fn set_dual_control(ctx: Context<SetDualControl>, dual_control_level: u8, pending_change_ttl: i64) -> Result<()> {
+        handle_set_dual_control(ctx, dual_control_level, pending_change_ttl)
+    }
+
+    pub fn propose_kyc_change(
+        ctx: Context<ProposeKycChange>,
+        kyc_level: Option<u8>,
+        flags_to_set: u8,
+        flags_to_clear: u8
+    ) -> Result<()> {
+        handle_propose_kyc_change(ctx, kyc_level, flags_to_set, flags_to_clear)
+    }
+
+    pub fn approve_kyc_change(ctx: Context<ApproveKycChange>) -> Result<()> {
+        handle_approve_kyc_change(ctx)
+    }
+
+    pub fn cancel_kyc_change(ctx: Context<CancelKycChange>) -> Result<()> {
+        handle_cancel_kyc_change(ctx)
+    }
+
+    pub fn batch_initialize_user_kyc<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchKyc<'info>>,
+        entries: Vec<BatchKycEntry>
+    ) -> Result<Vec<BatchKycResult>> {
+        handle_batch_initialize_user_kyc(ctx, entries)
+    }
+
+    pub fn batch_update_user_kyc<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchKyc<'info>>,
+        updates: Vec<BatchKycUpdate>
+    ) -> Result<Vec<BatchKycResult>> {
+        handle_batch_update_user_kyc(ctx, updates)
+    }
+
+    pub fn close_user_kyc(ctx: Context<CloseUserKyc>) -> Result<()> {
+        handle_close_user_kyc(ctx)
+    }
+
+    pub fn set_identity_resolution(ctx: Context<SetIdentityResolution>, enabled: bool) -> Result<()> {
+        handle_set_identity_resolution(ctx, enabled)
+    }
+
+    pub fn link_wallet(ctx: Context<LinkWallet>) -> Result<()> {
+        handle_link_wallet(ctx)
+    }
+
+    pub fn unlink_wallet(ctx: Context<UnlinkWallet>) -> Result<()> {
+        handle_unlink_wallet(ctx)
+    }
+
+    pub fn set_compliance_metadata(ctx: Context<SetComplianceMetadata>, field: ComplianceMetadata) -> Result<()> {
+        handle_set_compliance_metadata(ctx, field)
     }
 }
@@ -1,7 +1,12 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token_2022::spl_token_2022::{
+    extension::{ BaseStateWithExtensions, StateWithExtensions },
+    state::Mint as MintState,
+};
 use spl_token_metadata_interface::state::TokenMetadata;
 
 #[account]
+#[derive(Default)]
 pub struct UserKYC {
     pub user: Pubkey,
     pub kyc_level: u8,
@@ -13,12 +18,31 @@ pub struct UserKYC {
     pub last_reset_day: i64,
     pub last_reset_month: i64,
     pub country: [u8; 2],
+    /// Superseded by `subdivision`, always zero from version 4
     pub state: [u8; 2],
     pub city: [u8; 32],
+    pub verified_at: i64,
+    /// 0 when the verification does not expire
+    pub expires_at: i64,
+    pub version: u8,
+    /// Private records keep no plaintext location, only the identity hash and country groups
+    pub is_private: bool,
+    /// Salted hash of the full off-chain identity record
+    pub identity_hash: [u8; 32],
+    pub country_group_mask: u32,
+    pub investor_class: u8,
+    /// Regulatory qualifications, see the `INVESTOR_*` flags. Zero means nothing has been verified
+    pub investor_flags: u8,
+    /// ISO 3166-2 code after the country, zero padded, e.g. `971` for FR-971
+    pub subdivision: [u8; 3],
+    /// Holder positions with a nonzero balance, the record cannot be closed while any is open
+    pub open_positions: u32,
 }
 
 impl UserKYC {
-    pub const LEN: usize = 32 + 1 + 1 + 8 + 1 + 8 + 8 + 8 + 8 + 2 + 2 + 32;
+    /// Fields are only ever appended, so older records migrate by zero-extending the account
+    pub const VERSION: u8 = 5;
+    pub const LEN: usize = 32 + 1 + 1 + 8 + 1 + 8 + 8 + 8 + 8 + 2 + 2 + 32 + 8 + 8 + 1 + 1 + 32 + 4 + 1 + 1 + 3 + 4;
     pub const UNVERIFIED: u8 = 0;
     pub const BASIC: u8 = 1;
     pub const ENHANCED: u8 = 2;
@@ -27,6 +51,24 @@ impl UserKYC {
     pub const FLAG_PEP: u8 = 0x02;
     pub const FLAG_FROZEN: u8 = 0x04;
     pub const FLAG_EXPIRED: u8 = 0x08;
+    /// Raised by the hook on suspicious transfer patterns, cleared by an officer
+    pub const FLAG_REVIEW: u8 = 0x10;
+    /// Flags only a second officer may clear
+    pub const DUAL_CONTROL_FLAGS: u8 = Self::FLAG_SANCTIONS | Self::FLAG_FROZEN;
+    /// Flags set on a wallet's keys rather than the person behind them
+    pub const WALLET_FLAGS: u8 = Self::FLAG_FROZEN;
+    pub const CLASS_RETAIL: u8 = 0;
+    pub const CLASS_PROFESSIONAL: u8 = 1;
+    pub const CLASS_INSTITUTIONAL: u8 = 2;
+    /// Issuer affiliates, e.g. Rule 144 control persons
+    pub const CLASS_AFFILIATE: u8 = 3;
+    pub const INVESTOR_ACCREDITED: u8 = 0x01;
+    pub const INVESTOR_QUALIFIED_PURCHASER: u8 = 0x02;
+    /// US and non-US status are separate bits, so an unclassified holder fails both Reg S and Reg D checks
+    pub const INVESTOR_US_PERSON: u8 = 0x04;
+    pub const INVESTOR_NON_US_PERSON: u8 = 0x08;
+    pub const INVESTOR_FLAGS_MASK: u8 = 0x0f;
+    pub const MAX_RISK_SCORE: u8 = 100;
 
     pub fn is_sanctioned(&self) -> bool {
         (self.flags & Self::FLAG_SANCTIONS) != 0
@@ -34,11 +76,69 @@ impl UserKYC {
     pub fn is_frozen(&self) -> bool {
         (self.flags & Self::FLAG_FROZEN) != 0
     }
-    pub fn is_expired(&self) -> bool {
-        (self.flags & Self::FLAG_EXPIRED) != 0
+    pub fn is_pep(&self) -> bool {
+        (self.flags & Self::FLAG_PEP) != 0
     }
-    pub fn is_eligible_for_trading(&self) -> bool {
-        self.kyc_level >= Self::BASIC && !self.is_sanctioned() && !self.is_frozen() && !self.is_expired()
+    pub fn has_investor_flags(&self, required: u8) -> bool {
+        (self.investor_flags & required) == required
+    }
+    /// A person is either US or non-US, never both
+    pub fn validate_investor_flags(investor_flags: u8) -> Result<()> {
+        let residency = Self::INVESTOR_US_PERSON | Self::INVESTOR_NON_US_PERSON;
+        require!(
+            investor_flags & !Self::INVESTOR_FLAGS_MASK == 0 && investor_flags & residency != residency,
+            crate::error::CustomError::InvalidInvestorFlags
+        );
+        Ok(())
+    }
+    pub fn is_expired(&self, now: i64) -> bool {
+        (self.flags & Self::FLAG_EXPIRED) != 0 || (self.expires_at != 0 && now >= self.expires_at)
+    }
+    /// Lapsed by time only, and still within `grace_period` seconds of `expires_at`
+    pub fn in_grace_period(&self, now: i64, grace_period: i64) -> bool {
+        (self.flags & Self::FLAG_EXPIRED) == 0 &&
+            self.expires_at != 0 &&
+            now >= self.expires_at &&
+            now < self.expires_at.saturating_add(grace_period)
+    }
+    /// During the grace period only outgoing transfers (sells, withdrawals) are allowed
+    pub fn is_eligible_for_trading(&self, now: i64, grace_period: i64, outgoing: bool) -> bool {
+        self.kyc_level >= Self::BASIC &&
+            !self.is_sanctioned() &&
+            !self.is_frozen() &&
+            (!self.is_expired(now) || (outgoing && self.in_grace_period(now, grace_period)))
+    }
+
+    /// This record moved to `new_owner` after the wallet's keys were lost
+    /// The record under a recovered wallet, without the flags that belonged to the lost keys. Positions
+    /// open as each mint is recovered into the new wallet.
+    pub fn recovered_to(&self, new_owner: Pubkey, now: i64) -> UserKYC {
+        UserKYC {
+            user: new_owner,
+            last_updated: now,
+            flags: self.flags & !Self::WALLET_FLAGS,
+            open_positions: 0,
+            ..self.clone()
+        }
+    }
+
+    /// Follows one of the holder's positions moving from `old_balance` to `new_balance`, returning
+    /// whether it opened or closed
+    pub fn record_position_change(&mut self, old_balance: u64, new_balance: u64) -> bool {
+        match (old_balance > 0, new_balance > 0) {
+            (false, true) => self.open_positions = self.open_positions.saturating_add(1),
+            (true, false) => self.open_positions = self.open_positions.saturating_sub(1),
+            _ => {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn verify(&mut self, now: i64, validity_period: i64) {
+        self.verified_at = now;
+        self.expires_at = if validity_period > 0 { now.saturating_add(validity_period) } else { 0 };
+        self.flags &= !Self::FLAG_EXPIRED;
     }
 
     pub fn update_daily_volume(&mut self, current_day: i64, amount: u64) {
@@ -60,10 +160,20 @@ impl UserKYC {
         String::from_utf8_lossy(&self.country).trim_end_matches('\0').to_string()
     }
     pub fn get_state_str(&self) -> String {
-        String::from_utf8_lossy(&self.state).trim_end_matches('\0').to_string()
+        String::from_utf8_lossy(&self.subdivision).trim_end_matches('\0').to_string()
+    }
+    pub fn country_groups(&self) -> u32 {
+        if self.is_private { self.country_group_mask } else { CountryGroups::of(&self.country) }
     }
-    pub fn get_city_str(&self) -> String {
-        String::from_utf8_lossy(&self.city).trim_end_matches('\0').to_string()
+
+    pub fn make_private(&mut self, identity_hash: [u8; 32], country_group_mask: u32) {
+        self.is_private = true;
+        self.identity_hash = identity_hash;
+        self.country_group_mask = country_group_mask;
+        self.country = [0; 2];
+        self.state = [0; 2];
+        self.subdivision = [0; 3];
+        self.city = [0; 32];
     }
 
     pub fn set_country(&mut self, country: &str) {
@@ -74,9 +184,31 @@ impl UserKYC {
     }
     pub fn set_state(&mut self, state: &str) {
         self.state = [0; 2];
+        self.subdivision = [0; 3];
         let b = state.as_bytes();
-        let len = b.len().min(2);
-        self.state[..len].copy_from_slice(&b[..len]);
+        let len = b.len().min(3);
+        self.subdivision[..len].copy_from_slice(&b[..len]);
+    }
+    /// Moves a pre-version 4 two-character `state` into `subdivision`
+    pub fn migrate_subdivision(&mut self) {
+        if self.subdivision == [0; 3] {
+            self.subdivision[..2].copy_from_slice(&self.state);
+        }
+        self.state = [0; 2];
+    }
+
+    /// Upper case ISO 3166-1 alpha-2 code
+    pub fn validate_country(country: &str) -> Result<()> {
+        let valid = <&[u8; 2]>::try_from(country.as_bytes()).is_ok_and(crate::iso3166::is_country);
+        require!(valid, crate::error::CustomError::InvalidCountryCode);
+        Ok(())
+    }
+    /// Upper case ISO 3166-2 subdivision of `country`, or empty for none
+    pub fn validate_subdivision(country: &str, state: &str) -> Result<()> {
+        let valid = state.is_empty() ||
+            <&[u8; 2]>::try_from(country.as_bytes()).is_ok_and(|c| crate::iso3166::is_subdivision(c, state));
+        require!(valid, crate::error::CustomError::InvalidStateCode);
+        Ok(())
     }
     pub fn set_city(&mut self, city: &str) {
         self.city = [0; 32];
@@ -86,83 +218,1243 @@ impl UserKYC {
     }
 }
 
+/// Per-mint compliance rules, run in order by the transfer hook
+#[account]
+pub struct MintPolicy {
+    pub mint: Pubkey,
+    /// Issuer, the mint's transfer hook authority at creation
+    pub authority: Pubkey,
+    pub bump: u8,
+    /// Bumped on every change to `modules`, recorded in the transfer journal
+    pub version: u32,
+    /// KYC, caps and counters follow the identities wallets are linked to, see `set_identity_resolution`
+    pub resolves_identities: bool,
+    pub modules: Vec<ComplianceModule>,
+}
+
+impl MintPolicy {
+    pub const MAX_MODULES: usize = 16;
+
+    /// Account size for `modules`, the account is resized whenever they change
+    pub fn space(modules: &[ComplianceModule]) -> usize {
+        8 + 32 + 32 + 1 + 4 + 1 + 4 + modules.iter().map(ComplianceModule::size).sum::<usize>()
+    }
+
+    /// Replaces the module of the same kind in place, or appends it to the end of the chain
+    pub fn set_module(&mut self, module: ComplianceModule) {
+        match self.modules.iter_mut().find(|m| std::mem::discriminant(*m) == std::mem::discriminant(&module)) {
+            Some(existing) => {
+                *existing = module;
+            }
+            None => self.modules.push(module),
+        }
+    }
+
+    /// Drops the module of the same kind as `module`, its fields are ignored
+    pub fn remove_module(&mut self, module: &ComplianceModule) {
+        self.modules.retain(|m| std::mem::discriminant(m) != std::mem::discriminant(module));
+    }
+
+    pub fn validate_modules(modules: &[ComplianceModule]) -> Result<()> {
+        require!(modules.len() <= Self::MAX_MODULES, crate::error::CustomError::TooManyComplianceModules);
+        for (i, module) in modules.iter().enumerate() {
+            module.validate()?;
+            require!(
+                modules[..i].iter().all(|m| std::mem::discriminant(m) != std::mem::discriminant(module)),
+                crate::error::CustomError::InvalidComplianceModule
+            );
+        }
+        Ok(())
+    }
+}
+
+/// One rule of a mint's compliance chain. Variants are append-only, their index is the on-chain tag.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum ComplianceModule {
+    /// Minimum KYC level on both sides, including expiry and grace period
+    KycLevel {
+        min_level: u8,
+    },
+    /// Country rules from the mint metadata, plus a country group mask (0 for none)
+    Geography {
+        allowed_country_groups: u32,
+    },
+    /// Outgoing volume per holder in base units, 0 for no limit
+    Volume {
+        daily_limit: u64,
+        monthly_limit: u64,
+    },
+    /// Holding period in seconds before received tokens may be sent on, indexed by investor class
+    HoldingPeriod {
+        lockup_periods: [i64; 4],
+    },
+    /// Cap table limits, needs the mint's holder registry. 0 for no limit
+    MaxHolders {
+        max_holders: u32,
+        max_holder_bps: u16,
+    },
+    /// Bitmask of investor classes allowed to send or receive
+    InvestorClass {
+        allowed_classes: u8,
+    },
+    /// Only holders whitelisted in their `HolderStatus`
+    Whitelist,
+    /// `UserKYC::INVESTOR_*` flags the receiving holder must all carry, e.g. accredited for Reg D 506(c)
+    /// or non-US person for Reg S
+    InvestorRequirements {
+        required_flags: u8,
+    },
+    /// Enhanced due diligence for politically exposed persons: a higher KYC level on both sides and
+    /// tighter outgoing volume limits (0 for none) on top of any `Volume` module
+    PoliticallyExposed {
+        min_level: u8,
+        daily_limit: u64,
+        monthly_limit: u64,
+    },
+    /// Actions by the highest `risk_score` among the parties: below `limit_from` allowed, then up to
+    /// `max_amount` per transfer, from `approval_from` only with a `TransferApproval`, from `block_from`
+    /// never. A threshold above `UserKYC::MAX_RISK_SCORE` disables its band.
+    RiskBands {
+        limit_from: u8,
+        max_amount: u64,
+        approval_from: u8,
+        block_from: u8,
+    },
+    /// Allowed (sender, receiver) jurisdiction pairs between two non-exempt holders, anything unlisted
+    /// is rejected. Domestic transfers need their own route.
+    CrossBorder {
+        routes: Vec<CountryRoute>,
+    },
+    /// Weekly sessions in the mint's local time, closed on days in its `HolidayCalendar`. Transfers to
+    /// holders with `HolderStatus::FLAG_SETTLEMENT` go through after hours.
+    TradingSessions {
+        /// Fixed offset from UTC, daylight saving needs the windows updated
+        utc_offset_minutes: i16,
+        windows: Vec<SessionWindow>,
+    },
+    /// Transfers of at least `threshold` base units need an unconsumed `TravelRuleRecord` for the
+    /// exact amount, exempt parties included
+    TravelRule {
+        threshold: u64,
+    },
+    /// Structuring detection on outgoing transfers. Reaching a `review` count flags the sender for review
+    /// and raises its risk score once, going over a `block` count rejects the transfer. Put it before
+    /// `RiskBands` for the raised score to apply straight away.
+    Velocity {
+        reporting_threshold: u64,
+        /// Transfers from this share of `reporting_threshold` up to it count as near-threshold
+        near_threshold_bps: u16,
+        review: VelocityLimits,
+        block: VelocityLimits,
+        risk_increase: u8,
+    },
+    /// Minimum KYC level of an approved delegate moving a holder's tokens, on top of the owner's checks.
+    /// Exempt delegates are not checked.
+    DelegateKyc {
+        min_level: u8,
+    },
+}
+
+impl ComplianceModule {
+    /// Rules applied to mints without a policy
+    pub fn default_chain() -> Vec<Self> {
+        vec![
+            Self::KycLevel { min_level: UserKYC::BASIC },
+            Self::Geography { allowed_country_groups: 0 },
+            Self::PoliticallyExposed { min_level: UserKYC::ENHANCED, daily_limit: 0, monthly_limit: 0 }
+        ]
+    }
+
+    /// Whether the module reads the holder's balance or keeps per-holder counters in `HolderPosition`
+    pub fn uses_holder_position(&self) -> bool {
+        match self {
+            Self::Volume { .. } | Self::Velocity { .. } | Self::HoldingPeriod { .. } => true,
+            Self::PoliticallyExposed { daily_limit, monthly_limit, .. } => *daily_limit > 0 || *monthly_limit > 0,
+            _ => false,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        1 +
+            (match self {
+                Self::KycLevel { .. } => 1,
+                Self::Geography { .. } => 4,
+                Self::Volume { .. } => 8 + 8,
+                Self::HoldingPeriod { .. } => 8 * 4,
+                Self::MaxHolders { .. } => 4 + 2,
+                Self::InvestorClass { .. } => 1,
+                Self::Whitelist => 0,
+                Self::InvestorRequirements { .. } => 1,
+                Self::PoliticallyExposed { .. } => 1 + 8 + 8,
+                Self::RiskBands { .. } => 1 + 8 + 1 + 1,
+                Self::CrossBorder { routes } => 4 + routes.iter().map(CountryRoute::size).sum::<usize>(),
+                Self::TradingSessions { windows, .. } => 2 + 4 + windows.len() * SessionWindow::LEN,
+                Self::TravelRule { .. } => 8,
+                Self::Velocity { .. } => 8 + 2 + VelocityLimits::LEN * 2 + 1,
+                Self::DelegateKyc { .. } => 1,
+            })
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        use crate::error::CustomError;
+        match self {
+            Self::KycLevel { min_level } |
+            Self::PoliticallyExposed { min_level, .. } |
+            Self::DelegateKyc { min_level } => {
+                require!((UserKYC::BASIC..=UserKYC::INSTITUTIONAL).contains(min_level), CustomError::InvalidKycLevel);
+            }
+            Self::HoldingPeriod { lockup_periods } => {
+                require!(lockup_periods.iter().all(|p| *p >= 0), CustomError::InvalidLockupPeriod);
+            }
+            Self::MaxHolders { max_holder_bps, .. } => {
+                require!(*max_holder_bps <= 10_000, CustomError::InvalidHolderLimit);
+            }
+            Self::InvestorClass { allowed_classes } => {
+                require!(*allowed_classes != 0 && *allowed_classes < 1 << 4, CustomError::InvalidInvestorClass);
+            }
+            Self::InvestorRequirements { required_flags } => {
+                require!(*required_flags != 0, CustomError::InvalidInvestorFlags);
+                UserKYC::validate_investor_flags(*required_flags)?;
+            }
+            Self::RiskBands { limit_from, approval_from, block_from, .. } => {
+                require!(
+                    limit_from <= approval_from &&
+                        approval_from <= block_from &&
+                        *block_from <= UserKYC::MAX_RISK_SCORE + 1,
+                    CustomError::InvalidRiskBands
+                );
+            }
+            Self::CrossBorder { routes } => {
+                require!(
+                    !routes.is_empty() && routes.len() <= CountryRoute::MAX_ROUTES,
+                    CustomError::InvalidCrossBorderRoute
+                );
+                for route in routes {
+                    route.sender.validate()?;
+                    route.receiver.validate()?;
+                }
+            }
+            Self::TradingSessions { utc_offset_minutes, windows } => {
+                SessionWindow::validate_schedule(*utc_offset_minutes, windows)?;
+            }
+            Self::TravelRule { threshold } => {
+                require!(*threshold > 0, CustomError::InvalidTravelRuleThreshold);
+            }
+            Self::Velocity { reporting_threshold, near_threshold_bps, risk_increase, .. } => {
+                require!(
+                    *reporting_threshold > 0 &&
+                        (1..=10_000).contains(near_threshold_bps) &&
+                        *risk_increase <= UserKYC::MAX_RISK_SCORE,
+                    CustomError::InvalidVelocityLimits
+                );
+            }
+            Self::Geography { .. } | Self::Volume { .. } | Self::Whitelist => {}
+        }
+        Ok(())
+    }
+}
+
+/// Counts per holder for a `Velocity` module, 0 for no limit
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq)]
+pub struct VelocityLimits {
+    pub daily_transfers: u32,
+    pub daily_near_threshold: u32,
+    pub monthly_near_threshold: u32,
+}
+
+impl VelocityLimits {
+    pub const LEN: usize = 4 + 4 + 4;
+
+    fn limits(&self) -> [u32; 3] {
+        [self.daily_transfers, self.daily_near_threshold, self.monthly_near_threshold]
+    }
+
+    pub fn reached_by(&self, position: &HolderPosition, now: i64) -> bool {
+        self.limits()
+            .iter()
+            .zip(position.velocity_counts(now))
+            .any(|(limit, count)| *limit != 0 && count >= *limit)
+    }
+
+    pub fn exceeded_by(&self, position: &HolderPosition, now: i64) -> bool {
+        self.limits()
+            .iter()
+            .zip(position.velocity_counts(now))
+            .any(|(limit, count)| *limit != 0 && count > *limit)
+    }
+}
+
+/// Events over the last `period` seconds, estimated from two fixed buckets: the current one in full and
+/// the share of the previous one still inside the window. Unlike a bucket that resets at a fixed time,
+/// activity just before the boundary keeps counting right after it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct SlidingCount {
+    /// `now / period` of `current`
+    pub bucket: i64,
+    pub current: u32,
+    pub previous: u32,
+}
+
+impl SlidingCount {
+    pub const LEN: usize = 8 + 4 + 4;
+
+    /// The counts as of `bucket`
+    fn rolled(&self, bucket: i64) -> (u32, u32) {
+        match bucket - self.bucket {
+            0 => (self.current, self.previous),
+            1 => (0, self.current),
+            _ => (0, 0),
+        }
+    }
+
+    pub fn add(&mut self, now: i64, period: i64) {
+        let bucket = now.div_euclid(period);
+        let (current, previous) = self.rolled(bucket);
+        *self = Self { bucket, current: current.saturating_add(1), previous };
+    }
+
+    /// Rounded up, so the estimate never undercounts the window
+    pub fn count(&self, now: i64, period: i64) -> u32 {
+        let (current, previous) = self.rolled(now.div_euclid(period));
+        let remaining = (period - now.rem_euclid(period)) as u64;
+        let carried = ((previous as u64) * remaining).div_ceil(period as u64);
+        current.saturating_add(carried as u32)
+    }
+}
+
+/// One side of a `CountryRoute`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum Jurisdiction {
+    /// ISO 3166-1 alpha-2 code, never matches private records
+    Country([u8; 2]),
+    /// Any of the `CountryGroups` in the mask
+    Groups(u32),
+    Any,
+}
+
+impl Jurisdiction {
+    pub fn size(&self) -> usize {
+        1 +
+            (match self {
+                Self::Country(_) => 2,
+                Self::Groups(_) => 4,
+                Self::Any => 0,
+            })
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let valid = match self {
+            Self::Country(code) => crate::iso3166::is_country(code),
+            Self::Groups(mask) => *mask != 0,
+            Self::Any => true,
+        };
+        require!(valid, crate::error::CustomError::InvalidCrossBorderRoute);
+        Ok(())
+    }
+
+    pub fn contains(&self, user_kyc: &UserKYC) -> bool {
+        match self {
+            Self::Country(code) => !user_kyc.is_private && user_kyc.country == *code,
+            Self::Groups(mask) => user_kyc.country_groups() & mask != 0,
+            Self::Any => true,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct CountryRoute {
+    pub sender: Jurisdiction,
+    pub receiver: Jurisdiction,
+}
+
+impl CountryRoute {
+    pub const MAX_ROUTES: usize = 32;
+
+    pub fn size(&self) -> usize {
+        self.sender.size() + self.receiver.size()
+    }
+
+    pub fn allows(&self, sender: &UserKYC, receiver: &UserKYC) -> bool {
+        self.sender.contains(sender) && self.receiver.contains(receiver)
+    }
+}
+
+/// Opening hours on a set of weekdays, in minutes since local midnight
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct SessionWindow {
+    /// Bit 0 is Monday through bit 6 Sunday
+    pub days: u8,
+    pub open_minute: u16,
+    /// Exclusive, at most 1440
+    pub close_minute: u16,
+}
+
+impl SessionWindow {
+    pub const LEN: usize = 1 + 2 + 2;
+    pub const MAX_WINDOWS: usize = 14;
+    const MINUTES_PER_DAY: u16 = 24 * 60;
+
+    pub fn is_valid(&self) -> bool {
+        self.days != 0 && self.days < 1 << 7 && self.open_minute < self.close_minute &&
+            self.close_minute <= Self::MINUTES_PER_DAY
+    }
+
+    /// A weekly schedule as `TradingSessions` takes it
+    pub fn validate_schedule(utc_offset_minutes: i16, windows: &[Self]) -> Result<()> {
+        require!(
+            (-14 * 60..=14 * 60).contains(&utc_offset_minutes) &&
+                !windows.is_empty() &&
+                windows.len() <= Self::MAX_WINDOWS &&
+                windows.iter().all(Self::is_valid),
+            crate::error::CustomError::InvalidTradingSession
+        );
+        Ok(())
+    }
+
+    /// Whether a window is open at `now` on a day that is not a holiday
+    pub fn in_session(windows: &[Self], utc_offset_minutes: i16, holidays: Option<&HolidayCalendar>, now: i64) -> bool {
+        let local = now.saturating_add((utc_offset_minutes as i64) * 60);
+        let day = local.div_euclid(86400);
+        if holidays.is_some_and(|h| h.is_holiday(day)) {
+            return false;
+        }
+        // 1970-01-01 was a Thursday
+        let weekday = (day + 3).rem_euclid(7) as u32;
+        let minute = (local.rem_euclid(86400) / 60) as u16;
+        windows
+            .iter()
+            .any(|w| w.days & (1 << weekday) != 0 && w.open_minute <= minute && minute < w.close_minute)
+    }
+}
+
+/// Market holidays of one mint, as local day numbers since the epoch
+#[account]
+#[derive(Default)]
+pub struct HolidayCalendar {
+    pub mint: Pubkey,
+    pub days: Vec<i64>,
+    pub bump: u8,
+}
+
+impl HolidayCalendar {
+    pub const MAX_DAYS: usize = 64;
+    pub const LEN: usize = 32 + 4 + 8 * Self::MAX_DAYS + 1;
+
+    pub fn is_holiday(&self, day: i64) -> bool {
+        self.days.contains(&day)
+    }
+}
+
+/// Issuer's view of one holder of its mint, kept apart from the global `UserKYC` record
+#[account]
+#[derive(Default)]
+pub struct HolderStatus {
+    pub mint: Pubkey,
+    pub holder: Pubkey,
+    pub flags: u8,
+    /// Overrides `UserKYC::investor_class` for this mint
+    pub investor_class: Option<u8>,
+    pub bump: u8,
+}
+
+impl HolderStatus {
+    pub const LEN: usize = 32 + 32 + 1 + 2 + 1;
+    pub const FLAG_FROZEN: u8 = 0x01;
+    pub const FLAG_WHITELISTED: u8 = 0x02;
+    /// Settlement account such as an issuer redemption wallet, receives outside trading sessions
+    pub const FLAG_SETTLEMENT: u8 = 0x04;
+    pub const FLAGS_MASK: u8 = Self::FLAG_FROZEN | Self::FLAG_WHITELISTED | Self::FLAG_SETTLEMENT;
+
+    pub fn validate_flags(flags: u8) -> Result<()> {
+        require!(flags & !Self::FLAGS_MASK == 0, crate::error::CustomError::InvalidHolderStatusFlags);
+        Ok(())
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        (self.flags & Self::FLAG_FROZEN) != 0
+    }
+    pub fn is_whitelisted(&self) -> bool {
+        (self.flags & Self::FLAG_WHITELISTED) != 0
+    }
+    pub fn is_settlement(&self) -> bool {
+        (self.flags & Self::FLAG_SETTLEMENT) != 0
+    }
+    pub fn investor_class(&self, user_kyc: &UserKYC) -> u8 {
+        self.investor_class.unwrap_or(user_kyc.investor_class)
+    }
+
+    pub fn check(&self) -> Result<()> {
+        require!(!self.is_frozen(), crate::error::CustomError::HolderFrozenForMint);
+        Ok(())
+    }
+}
+
+/// Cap table of one mint; holders are non-exempt owners with a non-zero `HolderPosition`
+#[account]
+#[derive(Default)]
+pub struct HolderRegistry {
+    pub mint: Pubkey,
+    pub holder_count: u32,
+    pub bump: u8,
+}
+
+impl HolderRegistry {
+    pub const LEN: usize = 32 + 4 + 1;
+
+    /// Source leaves before destination joins, so handing a whole position to a new holder fits under a full
+    /// cap. `max_holders` of 0 means no cap.
+    pub fn apply_transfer(&mut self, source_exits: bool, destination_joins: bool, max_holders: u32) -> Result<()> {
+        if source_exits {
+            self.holder_count = self.holder_count.saturating_sub(1);
+        }
+        if destination_joins {
+            require!(
+                max_holders == 0 || self.holder_count < max_holders,
+                crate::error::CustomError::HolderLimitExceeded
+            );
+            self.holder_count = self.holder_count
+                .checked_add(1)
+                .ok_or(crate::error::CustomError::HolderLimitExceeded)?;
+        }
+        Ok(())
+    }
+
+    /// `max_holder_bps` of 0 means no cap
+    pub fn check_ownership(balance: u64, supply: u64, max_holder_bps: u16) -> Result<()> {
+        require!(
+            max_holder_bps == 0 || (balance as u128) * 10_000 <= (supply as u128) * (max_holder_bps as u128),
+            crate::error::CustomError::HolderOwnershipLimitExceeded
+        );
+        Ok(())
+    }
+}
+
+/// What one holder owns of a mint across all their token accounts, so caps apply to owners rather than
+/// token accounts. Transfers keep it current, balance changes outside a transfer (issuance, redemption)
+/// are recorded with `sync_holder_position`. Volume limits are per mint, so the outgoing counters live here too.
+#[account]
+#[derive(Default)]
+pub struct HolderPosition {
+    pub mint: Pubkey,
+    pub holder: Pubkey,
+    pub balance: u64,
+    pub daily_volume: u64,
+    pub monthly_volume: u64,
+    pub last_reset_day: i64,
+    pub last_reset_month: i64,
+    /// Outgoing transfers for `Velocity` modules, all and just under the reporting threshold
+    pub daily_transfers: SlidingCount,
+    pub daily_near_threshold: SlidingCount,
+    pub monthly_near_threshold: SlidingCount,
+    pub bump: u8,
+}
+
+impl HolderPosition {
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 8 + 8 + 8 + 3 * SlidingCount::LEN + 1;
+    const DAY: i64 = 86400;
+    const MONTH: i64 = 30 * Self::DAY;
+
+    /// Counts an outgoing transfer in the rolling velocity windows
+    pub fn record_velocity(&mut self, now: i64, near_threshold: bool) {
+        self.daily_transfers.add(now, Self::DAY);
+        if near_threshold {
+            self.daily_near_threshold.add(now, Self::DAY);
+            self.monthly_near_threshold.add(now, Self::MONTH);
+        }
+    }
+
+    /// Transfers, near-threshold transfers over the last day and over the last 30 days
+    pub fn velocity_counts(&self, now: i64) -> [u32; 3] {
+        [
+            self.daily_transfers.count(now, Self::DAY),
+            self.daily_near_threshold.count(now, Self::DAY),
+            self.monthly_near_threshold.count(now, Self::MONTH),
+        ]
+    }
+
+    /// Adds an outgoing transfer to the rolling counters and checks them, a limit of 0 means none
+    pub fn record_volume(&mut self, amount: u64, now: i64, daily_limit: u64, monthly_limit: u64) -> Result<()> {
+        let (day, month) = (now / 86400, now / (86400 * 30));
+        if self.last_reset_day != day {
+            self.daily_volume = 0;
+            self.last_reset_day = day;
+        }
+        if self.last_reset_month != month {
+            self.monthly_volume = 0;
+            self.last_reset_month = month;
+        }
+        self.daily_volume = self.daily_volume.saturating_add(amount);
+        self.monthly_volume = self.monthly_volume.saturating_add(amount);
+        self.check_volume(daily_limit, monthly_limit)
+    }
+
+    /// Checks the counters as they stand, for a second set of limits on an already recorded transfer
+    pub fn check_volume(&self, daily_limit: u64, monthly_limit: u64) -> Result<()> {
+        require!(
+            (daily_limit == 0 || self.daily_volume <= daily_limit) &&
+                (monthly_limit == 0 || self.monthly_volume <= monthly_limit),
+            crate::error::CustomError::VolumeLimitExceeded
+        );
+        Ok(())
+    }
+
+    /// A token account seen holding more than the position was funded before the position existed
+    pub fn reconcile(&mut self, seen_balance: u64) {
+        self.balance = self.balance.max(seen_balance);
+    }
+
+    /// Moves `amount` from one holder to another, returning whether `from` leaves and `to` joins the cap table
+    pub fn move_balance(from: &mut Self, to: &mut Self, amount: u64) -> (bool, bool) {
+        let exits = from.balance > 0 && from.balance <= amount;
+        let joins = to.balance == 0 && amount > 0;
+        from.balance = from.balance.saturating_sub(amount);
+        to.balance = to.balance.saturating_add(amount);
+        (exits, joins)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Lot {
+    pub amount: u64,
+    pub acquired_at: i64,
+}
+
+/// FIFO of incoming transfers for one (owner, mint), oldest first
+#[account]
+#[derive(Default)]
+pub struct HoldingLots {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub lots: Vec<Lot>,
+    pub bump: u8,
+}
+
+impl HoldingLots {
+    pub const MAX_LOTS: usize = 16;
+    pub const LEN: usize = 32 + 32 + 4 + 16 * Self::MAX_LOTS + 1;
+
+    pub fn tracked(&self) -> u64 {
+        self.lots.iter().fold(0u64, |acc, l| acc.saturating_add(l.amount))
+    }
+
+    /// Lots already past `lockup_period` are dropped first, the balance they covered counts as untracked
+    /// and so unlocked. A list still full rejects the new lot, merging it would shift some lockup.
+    pub fn record(&mut self, amount: u64, now: i64, lockup_period: i64) -> Result<()> {
+        let unlocked = self.lots
+            .iter()
+            .take_while(|l| l.acquired_at.saturating_add(lockup_period) <= now)
+            .count();
+        self.lots.drain(..unlocked);
+        match self.lots.last_mut() {
+            Some(last) if last.acquired_at == now => {
+                last.amount = last.amount.saturating_add(amount);
+            }
+            _ => {
+                require!(self.lots.len() < Self::MAX_LOTS, crate::error::CustomError::HoldingLotsFull);
+                self.lots.push(Lot { amount, acquired_at: now });
+            }
+        }
+        Ok(())
+    }
+
+    /// Consumes `amount` out of a pre-transfer `balance`, oldest first. Balance not covered by
+    /// lots predates tracking and counts as unlocked.
+    pub fn release(&mut self, balance: u64, amount: u64, lockup_period: i64, now: i64) -> Result<()> {
+        // lots no longer backed by the balance left without a hook, e.g. burns
+        let excess = self.tracked().saturating_sub(balance);
+        self.consume(excess);
+
+        let untracked = balance.saturating_sub(self.tracked());
+        let unlocked = self.lots
+            .iter()
+            .take_while(|l| l.acquired_at.saturating_add(lockup_period) <= now)
+            .fold(untracked, |acc, l| acc.saturating_add(l.amount));
+        require!(amount <= unlocked, crate::error::CustomError::TokensLocked);
+        self.consume(amount.saturating_sub(untracked));
+        Ok(())
+    }
+
+    /// Merges lots carried over from another wallet, keeping them in acquisition order
+    pub fn absorb(&mut self, lots: &[Lot]) {
+        self.lots.extend_from_slice(lots);
+        self.lots.sort_by_key(|l| l.acquired_at);
+        while self.lots.len() > Self::MAX_LOTS {
+            let last = self.lots.pop().unwrap_or_default();
+            if let Some(prev) = self.lots.last_mut() {
+                prev.amount = prev.amount.saturating_add(last.amount);
+                prev.acquired_at = last.acquired_at;
+            }
+        }
+    }
+
+    fn consume(&mut self, mut amount: u64) {
+        while amount > 0 && !self.lots.is_empty() {
+            let take = self.lots[0].amount.min(amount);
+            self.lots[0].amount -= take;
+            amount -= take;
+            if self.lots[0].amount == 0 {
+                self.lots.remove(0);
+            }
+        }
+    }
+}
+
+/// Outcome of `check_transfer`; `reason` is the error code the hook would fail with, 0 when allowed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq)]
+pub struct TransferVerdict {
+    pub allowed: bool,
+    pub reason: u32,
+}
+
+/// Move of a lost wallet's holdings, proposed and approved by two different officers
+#[account]
+pub struct RecoveryRequest {
+    pub mint: Pubkey,
+    pub old_owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub proposed_by: Pubkey,
+    pub approved_by: Option<Pubkey>,
+    pub proposed_at: i64,
+    pub bump: u8,
+    pub expires_at: i64,
+}
+
+impl RecoveryRequest {
+    pub const LEN: usize = 32 + 32 + 32 + 32 + 1 + 32 + 8 + 1 + 8;
+
+    /// Officer who approved the request, once it is approved and before it expires
+    pub fn approval(&self, now: i64) -> Result<Pubkey> {
+        require!(now < self.expires_at, crate::error::CustomError::RecoveryExpired);
+        self.approved_by.ok_or(error!(crate::error::CustomError::RecoveryApprovalRequired))
+    }
+}
+
+/// Compliance sign-off for transfers from one holder to another, needed once `RiskBands` puts either
+/// party in the approval band
+#[account]
+#[derive(Default)]
+pub struct TransferApproval {
+    pub mint: Pubkey,
+    pub source_owner: Pubkey,
+    pub destination_owner: Pubkey,
+    /// Amount still covered, drawn down by each transfer
+    pub remaining_amount: u64,
+    /// At most `MAX_TTL` after approval
+    pub expires_at: i64,
+    pub approved_by: Pubkey,
+    pub bump: u8,
+}
+
+impl TransferApproval {
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 32 + 1;
+    /// Longest an approval may stay open, the parties' risk can change in the meantime
+    pub const MAX_TTL: i64 = 30 * 86400;
+
+    pub fn validate_expiry(expires_at: i64, now: i64) -> Result<()> {
+        require!(
+            now < expires_at && expires_at <= now.saturating_add(Self::MAX_TTL),
+            crate::error::CustomError::InvalidApprovalExpiry
+        );
+        Ok(())
+    }
+
+    pub fn draw(&mut self, amount: u64, now: i64) -> Result<()> {
+        require!(now < self.expires_at, crate::error::CustomError::TransferApprovalRequired);
+        self.remaining_amount = self.remaining_amount
+            .checked_sub(amount)
+            .ok_or(crate::error::CustomError::TransferApprovalExceeded)?;
+        Ok(())
+    }
+}
+
+/// Originator and beneficiary information for one large transfer between two holders. The hook
+/// resolves one slot per (mint, source, destination); the nonce orders successive records in it.
+#[account]
+#[derive(Default)]
+pub struct TravelRuleRecord {
+    pub mint: Pubkey,
+    pub source_owner: Pubkey,
+    pub destination_owner: Pubkey,
+    /// Strictly increasing per slot, so a consumed record cannot be replayed
+    pub nonce: u64,
+    pub amount: u64,
+    /// Hashes of the off-chain originator and beneficiary data held by the VASPs
+    pub originator_hash: [u8; 32],
+    pub beneficiary_hash: [u8; 32],
+    pub recorded_by: Pubkey,
+    pub recorded_at: i64,
+    pub consumed: bool,
+    pub bump: u8,
+}
+
+impl TravelRuleRecord {
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 32 + 32 + 32 + 8 + 1 + 1;
+
+    /// A pending record is only replaced once consumed or cancelled, so the transfer it was filed for
+    /// cannot lose it to a later one
+    pub fn check_replaceable(&self, nonce: u64) -> Result<()> {
+        require!(nonce > self.nonce, crate::error::CustomError::InvalidTravelRuleNonce);
+        require!(self.nonce == 0 || self.consumed, crate::error::CustomError::TravelRuleRecordPending);
+        Ok(())
+    }
+
+    pub fn consume(&mut self, amount: u64) -> Result<()> {
+        require!(!self.consumed && self.amount == amount, crate::error::CustomError::TravelRuleRecordRequired);
+        self.consumed = true;
+        Ok(())
+    }
+}
+
+#[zero_copy]
+#[derive(Default, Debug, PartialEq)]
+pub struct JournalEntry {
+    /// Position in the mint's journal
+    pub sequence: u64,
+    pub slot: u64,
+    pub amount: u64,
+    pub source_owner: Pubkey,
+    pub destination_owner: Pubkey,
+    pub policy_version: u32,
+    pub _padding: [u8; 4],
+}
+
+/// Append-only record of a mint's transfers, kept as a ring of two pages. Full pages are rolled into
+/// `JournalArchive` accounts; transfers stop rather than overwrite an unarchived page.
+#[account(zero_copy)]
+pub struct TransferJournal {
+    pub mint: Pubkey,
+    /// Entries ever written, the next goes to `entries[head % CAPACITY]`
+    pub head: u64,
+    /// Entries rolled into archive pages, always a whole number of pages
+    pub archived: u64,
+    pub entries: [JournalEntry; TransferJournal::CAPACITY],
+}
+
+impl TransferJournal {
+    pub const PAGE_LEN: usize = 32;
+    pub const CAPACITY: usize = 2 * Self::PAGE_LEN;
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    pub fn is_full(&self) -> bool {
+        self.head - self.archived >= Self::CAPACITY as u64
+    }
+
+    pub fn append(&mut self, mut entry: JournalEntry) -> Result<()> {
+        require!(!self.is_full(), crate::error::CustomError::TransferJournalFull);
+        entry.sequence = self.head;
+        self.entries[(self.head % Self::CAPACITY as u64) as usize] = entry;
+        self.head += 1;
+        Ok(())
+    }
+
+    /// Index of the oldest unarchived page
+    pub fn next_page(&self) -> u64 {
+        self.archived / Self::PAGE_LEN as u64
+    }
+
+    /// Takes the oldest page out of the ring once every entry in it has been written
+    pub fn archive_page(&mut self) -> Result<[JournalEntry; Self::PAGE_LEN]> {
+        require!(
+            self.head - self.archived >= Self::PAGE_LEN as u64,
+            crate::error::CustomError::TransferJournalPageNotFull
+        );
+        let start = (self.archived % Self::CAPACITY as u64) as usize;
+        let mut page = [JournalEntry::default(); Self::PAGE_LEN];
+        page.copy_from_slice(&self.entries[start..start + Self::PAGE_LEN]);
+        self.archived += Self::PAGE_LEN as u64;
+        Ok(page)
+    }
+}
+
+/// One archived page of a `TransferJournal`
+#[account(zero_copy)]
+pub struct JournalArchive {
+    pub mint: Pubkey,
+    pub page: u64,
+    pub entries: [JournalEntry; TransferJournal::PAGE_LEN],
+}
+
+impl JournalArchive {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+}
+
+/// Sensitive `UserKYC` change proposed by one officer, applied when a different officer approves it
+#[account]
+pub struct PendingKycChange {
+    pub user: Pubkey,
+    pub kyc_level: Option<u8>,
+    pub flags_to_set: u8,
+    pub flags_to_clear: u8,
+    pub proposed_by: Pubkey,
+    pub proposed_at: i64,
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+impl PendingKycChange {
+    pub const LEN: usize = 32 + 2 + 1 + 1 + 32 + 8 + 8 + 1;
+
+    pub fn apply(&self, user_kyc: &mut UserKYC, config: &ComplianceConfig, now: i64) {
+        if let Some(level) = self.kyc_level {
+            if level != user_kyc.kyc_level {
+                user_kyc.verify(now, config.validity_period(level));
+            }
+            user_kyc.kyc_level = level;
+        }
+        user_kyc.flags = (user_kyc.flags | self.flags_to_set) & !self.flags_to_clear;
+        user_kyc.last_updated = now;
+    }
+}
+
+/// One record to create in `batch_initialize_user_kyc`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct BatchKycEntry {
+    pub user: Pubkey,
+    pub kyc_level: u8,
+    pub country: String,
+    pub state: String,
+    pub city: String,
+}
+
+/// Changes `update_user_kyc` applies, `None` leaves the field as it is
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct UserKycUpdate {
+    pub kyc_level: Option<u8>,
+    pub risk_score: Option<u8>,
+    pub flags_to_set: Option<u8>,
+    pub flags_to_clear: Option<u8>,
+    pub country: Option<String>,
+    pub state: Option<String>,
+    pub city: Option<String>,
+}
+
+/// One change in `batch_update_user_kyc`, under the same dual control rules as `update_user_kyc`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct BatchKycUpdate {
+    pub user: Pubkey,
+    pub kyc_level: Option<u8>,
+    pub risk_score: Option<u8>,
+    pub flags_to_set: u8,
+    pub flags_to_clear: u8,
+}
+
+/// Outcome of one batch entry, returned in entry order
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq)]
+pub struct BatchKycResult {
+    pub user: Pubkey,
+    /// Error code the entry failed with, 0 when applied
+    pub error_code: u32,
+}
+
+impl BatchKycResult {
+    /// Keeps the results inside the 1024 byte return data
+    pub const MAX_ENTRIES: usize = 24;
+}
+
+/// Maps a wallet to the identity whose `UserKYC` covers it. An identity is an ordinary `UserKYC`
+/// keyed by an identity key instead of a wallet, so every wallet linked to it shares its level, flags
+/// and volume counters on mints that resolve identities.
+#[account]
+#[derive(Default)]
+pub struct WalletLink {
+    /// Kept first, the hook seeds the identity's `UserKYC` from these bytes
+    pub identity: Pubkey,
+    pub wallet: Pubkey,
+    pub linked_by: Pubkey,
+    pub linked_at: i64,
+    pub bump: u8,
+}
+
+impl WalletLink {
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 1;
+    /// Offset of `identity` in the account data
+    pub const IDENTITY_OFFSET: u8 = 8;
+}
+
+/// How many wallets are linked to an identity, so its `UserKYC` is not closed from under them
+#[account]
+#[derive(Default)]
+pub struct IdentityLinks {
+    pub identity: Pubkey,
+    pub linked_wallets: u32,
+    pub bump: u8,
+}
+
+impl IdentityLinks {
+    pub const LEN: usize = 32 + 4 + 1;
+}
+
+/// Coarse jurisdiction groups, the only location data kept on private KYC records
+pub struct CountryGroups;
+
+impl CountryGroups {
+    pub const US: u32 = 1 << 0;
+    pub const CANADA: u32 = 1 << 1;
+    pub const UK: u32 = 1 << 2;
+    pub const EU: u32 = 1 << 3;
+    pub const EFTA: u32 = 1 << 4;
+    pub const OTHER: u32 = 1 << 31;
+
+    const EU_MEMBERS: [&'static [u8; 2]; 27] = [
+        b"AT", b"BE", b"BG", b"HR", b"CY", b"CZ", b"DK", b"EE", b"FI", b"FR", b"DE", b"GR", b"HU", b"IE",
+        b"IT", b"LV", b"LT", b"LU", b"MT", b"NL", b"PL", b"PT", b"RO", b"SK", b"SI", b"ES", b"SE",
+    ];
+    const EFTA_MEMBERS: [&'static [u8; 2]; 4] = [b"IS", b"LI", b"NO", b"CH"];
+
+    pub fn of(country: &[u8; 2]) -> u32 {
+        match country {
+            b"US" => Self::US,
+            b"CA" => Self::CANADA,
+            b"GB" => Self::UK,
+            c if Self::EU_MEMBERS.contains(&c) => Self::EU,
+            c if Self::EFTA_MEMBERS.contains(&c) => Self::EFTA,
+            _ => Self::OTHER,
+        }
+    }
+}
+
+#[account]
+pub struct ComplianceConfig {
+    pub authority: Pubkey,
+    pub officers: Vec<Pubkey>,
+    /// Default KYC validity in seconds, indexed by KYC level; 0 means no expiry
+    pub kyc_validity: [i64; 4],
+    /// Seconds after expiry during which holders may still send but not receive
+    pub grace_period: i64,
+    pub bump: u8,
+    /// Level changes to or from this level or higher need a second officer, 0 for every level change
+    pub dual_control_level: u8,
+    /// Seconds a pending KYC change stays approvable, 0 for `DEFAULT_PENDING_CHANGE_TTL`
+    pub pending_change_ttl: i64,
+}
+
+impl ComplianceConfig {
+    pub const MAX_OFFICERS: usize = 10;
+    pub const LEN: usize = 32 + 4 + 32 * Self::MAX_OFFICERS + 8 * 4 + 8 + 1 + 1 + 8;
+    pub const DEFAULT_KYC_VALIDITY: [i64; 4] = [0, 2 * 365 * 86400, 365 * 86400, 365 * 86400];
+    pub const DEFAULT_PENDING_CHANGE_TTL: i64 = 3 * 86400;
+
+    pub fn is_officer(&self, key: &Pubkey) -> bool {
+        self.authority == *key || self.officers.contains(key)
+    }
+
+    pub fn validity_period(&self, kyc_level: u8) -> i64 {
+        self.kyc_validity.get(kyc_level as usize).copied().unwrap_or(0)
+    }
+
+    pub fn pending_change_ttl(&self) -> i64 {
+        if self.pending_change_ttl > 0 { self.pending_change_ttl } else { Self::DEFAULT_PENDING_CHANGE_TTL }
+    }
+
+    /// Lifting sanctions or a freeze, and level changes reaching `dual_control_level`, go through a
+    /// `PendingKycChange`. Imposing restrictions stays immediate.
+    pub fn requires_dual_control(&self, user_kyc: &UserKYC, new_kyc_level: Option<u8>, flags_to_clear: u8) -> bool {
+        let level_change = new_kyc_level.is_some_and(
+            |level| level != user_kyc.kyc_level && level.max(user_kyc.kyc_level) >= self.dual_control_level
+        );
+        let lifts_restriction = (flags_to_clear & user_kyc.flags & UserKYC::DUAL_CONTROL_FLAGS) != 0;
+        level_change || lifts_restriction
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ExemptEntry {
+    pub key: Pubkey,
+    pub kind: u8,
+}
+
+#[account]
+pub struct ExemptionRegistry {
+    pub entries: Vec<ExemptEntry>,
+    pub bump: u8,
+}
+
+impl ExemptionRegistry {
+    pub const MAX_ENTRIES: usize = 32;
+    pub const LEN: usize = 4 + (32 + 1) * Self::MAX_ENTRIES + 1;
+    /// Exempts a specific owner address, e.g. a pool authority PDA
+    pub const KIND_OWNER: u8 = 0;
+    /// Exempts every owner account owned by a program, e.g. escrow PDAs
+    pub const KIND_PROGRAM: u8 = 1;
+
+    pub fn is_exempt(&self, owner: &AccountInfo) -> bool {
+        self.entries.iter().any(|e| {
+            (e.kind == Self::KIND_OWNER && e.key == owner.key()) ||
+                (e.kind == Self::KIND_PROGRAM && e.key == *owner.owner)
+        })
+    }
+
+    pub fn add(&mut self, key: Pubkey, kind: u8) -> Result<()> {
+        require!(self.entries.len() < Self::MAX_ENTRIES, crate::error::CustomError::ExemptionRegistryFull);
+        require!(!self.entries.iter().any(|e| e.key == key), crate::error::CustomError::ExemptionAlreadyExists);
+        self.entries.push(ExemptEntry { key, kind });
+        Ok(())
+    }
+
+    pub fn remove(&mut self, key: Pubkey) -> Result<()> {
+        let index = self.entries
+            .iter()
+            .position(|e| e.key == key)
+            .ok_or(crate::error::CustomError::ExemptionNotFound)?;
+        self.entries.remove(index);
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct KycProvider {
+    pub signing_key: Pubkey,
+    /// Highest KYC level this provider may attest to
+    pub max_kyc_level: u8,
+}
+
+#[account]
+pub struct KycProviderRegistry {
+    pub providers: Vec<KycProvider>,
+    pub bump: u8,
+}
+
+impl KycProviderRegistry {
+    pub const MAX_PROVIDERS: usize = 16;
+    pub const LEN: usize = 4 + (32 + 1) * Self::MAX_PROVIDERS + 1;
+
+    pub fn get(&self, signing_key: &Pubkey) -> Option<&KycProvider> {
+        self.providers.iter().find(|p| p.signing_key == *signing_key)
+    }
+}
+
+/// Signed off-chain by a KYC provider and verified through the Ed25519 program
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct KycAttestation {
+    pub user: Pubkey,
+    pub kyc_level: u8,
+    pub country: [u8; 2],
+    /// ISO 3166-2 subdivision code, zero padded
+    pub state: [u8; 3],
+    pub issued_at: i64,
+    pub expires_at: i64,
+}
+
+impl KycAttestation {
+    pub const DOMAIN: &'static [u8] = b"rwa-kyc-attestation-v2";
+
+    /// Bytes the provider signs: domain, this program's id, then the borsh-encoded attestation
+    pub fn message(&self) -> Result<Vec<u8>> {
+        let mut message = Self::DOMAIN.to_vec();
+        message.extend_from_slice(crate::ID.as_ref());
+        self.serialize(&mut message)?;
+        Ok(message)
+    }
+}
+
+/// ISO 3166-2 subdivision, e.g. `US` and `CA` for US-CA
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct Subdivision {
+    pub country: [u8; 2],
+    pub code: String,
+}
+
+impl Subdivision {
+    pub fn is_valid(&self) -> bool {
+        crate::iso3166::is_subdivision(&self.country, &self.code)
+    }
+}
+
+/// Compliance field `set_compliance_metadata` writes into the mint's Token-2022 metadata. An empty value
+/// removes the field.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum ComplianceMetadata {
+    /// ISO 3166-1 alpha-2 codes
+    AllowedCountries(Vec<[u8; 2]>),
+    /// `CountryGroups` bitmask
+    AllowedCountryGroups(u32),
+    RestrictedStates(Vec<Subdivision>),
+}
+
+impl ComplianceMetadata {
+    pub fn validate(&self) -> Result<()> {
+        use crate::error::CustomError;
+        match self {
+            Self::AllowedCountries(countries) => {
+                require!(countries.iter().all(crate::iso3166::is_country), CustomError::InvalidCountryCode);
+            }
+            Self::RestrictedStates(subdivisions) => {
+                require!(subdivisions.iter().all(Subdivision::is_valid), CustomError::InvalidStateCode);
+            }
+            Self::AllowedCountryGroups(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Metadata keys with the values `Token2022MetadataParser::extract_rwa_metadata` reads back, `None`
+    /// for keys to remove
+    pub fn fields(&self) -> Vec<(&'static str, Option<String>)> {
+        let join = |codes: Vec<String>| (!codes.is_empty()).then(|| codes.join(","));
+        match self {
+            Self::AllowedCountries(countries) => {
+                let codes = countries.iter().map(|c| String::from_utf8_lossy(c).into_owned()).collect();
+                vec![(RwaMetadata::ALLOWED_COUNTRIES, join(codes))]
+            }
+            Self::AllowedCountryGroups(mask) => {
+                vec![(RwaMetadata::ALLOWED_COUNTRY_GROUPS, (*mask != 0).then(|| mask.to_string()))]
+            }
+            Self::RestrictedStates(subdivisions) => {
+                // matched against `{country}_{state}` of each holder
+                let codes = subdivisions
+                    .iter()
+                    .map(|s| format!("{}_{}", String::from_utf8_lossy(&s.country), s.code))
+                    .collect();
+                vec![(RwaMetadata::RESTRICTED_STATES, join(codes))]
+            }
+        }
+    }
+}
+
+/// Compliance fields read from the mint's metadata. Trading hours are not among them, the
+/// `TradingSessions` module enforces those.
 #[derive(Clone, Debug)]
 pub struct RwaMetadata {
     pub allowed_countries: Option<String>,
+    /// `CountryGroups` bitmask, evaluable for private KYC records
+    pub allowed_country_groups: Option<String>,
     pub restricted_states: Option<String>,
-    pub trading_hours: Option<String>,
-    pub timezone_offset: Option<String>,
     pub metadata_type: Option<String>,
     pub compliance_status: Option<String>,
 }
 
+impl RwaMetadata {
+    pub const ALLOWED_COUNTRIES: &'static str = "allowed_countries";
+    pub const ALLOWED_COUNTRY_GROUPS: &'static str = "allowed_country_groups";
+    pub const RESTRICTED_STATES: &'static str = "restricted_states";
+    pub const METADATA_TYPE: &'static str = "metadata_type";
+    pub const COMPLIANCE_STATUS: &'static str = "compliance_status";
+
+    /// Exact entry of a comma separated metadata list
+    pub fn list_contains(list: &str, code: &str) -> bool {
+        list.split(',').any(|entry| entry.trim() == code)
+    }
+}
+
 pub struct Token2022MetadataParser;
 impl Token2022MetadataParser {
+    /// Reads the metadata the mint stores in its own `TokenMetadata` extension
     pub fn parse_metadata_from_mint(account_data: &[u8]) -> Result<TokenMetadata> {
-        Self::extract_metadata_from_account_data(account_data)
-    }
-    fn extract_metadata_from_account_data(account_data: &[u8]) -> Result<TokenMetadata> {
-        let strings = Self::extract_ascii_strings(account_data);
-        let mut name = "Unknown Token".to_string();
-        let mut symbol = "UNK".to_string();
-        let mut uri = String::new();
-        let mut additional_metadata = Vec::new();
-        for s in &strings {
-            if s.starts_with("http") {
-                uri = s.clone();
-            } else if s.len() <= 10 && s.chars().all(|c| (c.is_ascii_uppercase() || c.is_ascii_digit())) {
-                symbol = s.clone();
-            } else if s.len() <= 50 && s.len() > 2 {
-                name = s.clone();
-            }
-        }
-        additional_metadata.push(("strings_found".to_string(), strings.len().to_string()));
-        let token_metadata = TokenMetadata {
-            update_authority: None.try_into().unwrap_or_default(),
-            mint: Pubkey::default(),
-            name,
-            symbol,
-            uri,
-            additional_metadata,
-        };
-        Ok(token_metadata)
-    }
-    fn extract_ascii_strings(data: &[u8]) -> Vec<String> {
-        let mut strings = Vec::new();
-        let mut cur = Vec::new();
-        for &b in data {
-            if b.is_ascii() && !b.is_ascii_control() && b != 0 {
-                cur.push(b);
-            } else if !cur.is_empty() && cur.len() >= 3 {
-                if let Ok(s) = String::from_utf8(cur.clone()) {
-                    let t = s.trim().to_string();
-                    if t.len() >= 3 {
-                        strings.push(t);
-                    }
-                }
-                cur.clear();
-            } else {
-                cur.clear();
-            }
-        }
-        if !cur.is_empty() && cur.len() >= 3 {
-            if let Ok(s) = String::from_utf8(cur) {
-                let t = s.trim().to_string();
-                if t.len() >= 3 {
-                    strings.push(t);
-                }
-            }
-        }
-        strings
+        let mint = StateWithExtensions::<MintState>::unpack(account_data)?;
+        Ok(mint.get_variable_len_extension::<TokenMetadata>()?)
+    }
+    pub fn get_metadata_field(metadata: &TokenMetadata, field_key: &str) -> Option<String> {
+        metadata.additional_metadata
+            .iter()
+            .find(|(key, _)| key == field_key)
+            .map(|(_, value)| value.clone())
     }
-    pub fn extract_rwa_metadata(_metadata: &TokenMetadata) -> RwaMetadata {
+    pub fn extract_rwa_metadata(metadata: &TokenMetadata) -> RwaMetadata {
         RwaMetadata {
-            allowed_countries: None,
-            restricted_states: None,
-            trading_hours: None,
-            timezone_offset: None,
-            metadata_type: None,
-            compliance_status: None,
+            allowed_countries: Self::get_metadata_field(metadata, RwaMetadata::ALLOWED_COUNTRIES),
+            allowed_country_groups: Self::get_metadata_field(metadata, RwaMetadata::ALLOWED_COUNTRY_GROUPS),
+            restricted_states: Self::get_metadata_field(metadata, RwaMetadata::RESTRICTED_STATES),
+            metadata_type: Self::get_metadata_field(metadata, RwaMetadata::METADATA_TYPE),
+            compliance_status: Self::get_metadata_field(metadata, RwaMetadata::COMPLIANCE_STATUS),
         }
     }
 }
@@ -0,0 +1,105 @@
+use anchor_lang::prelude::Pubkey;
+
+use crate::{ instructions::{ attestation_signer, parse_ed25519_signature }, state::KycAttestation };
+
+const HEADER_LEN: usize = 16;
+const PUBLIC_KEY_OFFSET: usize = HEADER_LEN;
+const SIGNATURE_OFFSET: usize = PUBLIC_KEY_OFFSET + 32;
+const MESSAGE_OFFSET: usize = SIGNATURE_OFFSET + 64;
+
+/// Ed25519 program instruction data with one signature, all parts inline
+fn ed25519_data(signer: &Pubkey, message: &[u8]) -> Vec<u8> {
+    let mut data = vec![1, 0];
+    for offset in [
+        SIGNATURE_OFFSET,
+        u16::MAX as usize,
+        PUBLIC_KEY_OFFSET,
+        u16::MAX as usize,
+        MESSAGE_OFFSET,
+        message.len(),
+        u16::MAX as usize,
+    ] {
+        data.extend_from_slice(&(offset as u16).to_le_bytes());
+    }
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(&[7; 64]);
+    data.extend_from_slice(message);
+    data
+}
+
+fn set_u16(data: &mut [u8], at: usize, value: u16) {
+    data[at..at + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn attestation() -> KycAttestation {
+    KycAttestation {
+        user: Pubkey::new_unique(),
+        kyc_level: 1,
+        country: *b"US",
+        state: *b"NY\0",
+        issued_at: 100,
+        expires_at: 200,
+    }
+}
+
+#[test]
+fn test_reads_signer_and_message_at_offsets() {
+    let signer = Pubkey::new_unique();
+    let data = ed25519_data(&signer, b"signed bytes");
+    let (parsed, message) = parse_ed25519_signature(&data).unwrap();
+    assert_eq!(parsed, signer);
+    assert_eq!(message, b"signed bytes");
+
+    // offsets are honoured, not assumed: move the message one byte later
+    let mut shifted = data.clone();
+    shifted.insert(MESSAGE_OFFSET, 0);
+    set_u16(&mut shifted, 10, (MESSAGE_OFFSET + 1) as u16);
+    assert_eq!(parse_ed25519_signature(&shifted).unwrap().1, b"signed bytes");
+}
+
+#[test]
+fn test_rejects_data_in_other_instructions() {
+    let data = ed25519_data(&Pubkey::new_unique(), b"signed bytes");
+    // signature, public key and message instruction indexes
+    for at in [4, 8, 14] {
+        let mut other = data.clone();
+        set_u16(&mut other, at, 0);
+        assert!(parse_ed25519_signature(&other).is_err());
+    }
+}
+
+#[test]
+fn test_rejects_malformed_headers_and_offsets() {
+    let data = ed25519_data(&Pubkey::new_unique(), b"signed bytes");
+    assert!(parse_ed25519_signature(&data[..HEADER_LEN - 1]).is_err());
+
+    let mut two_signatures = data.clone();
+    two_signatures[0] = 2;
+    assert!(parse_ed25519_signature(&two_signatures).is_err());
+
+    let mut message_past_end = data.clone();
+    set_u16(&mut message_past_end, 12, 13);
+    assert!(parse_ed25519_signature(&message_past_end).is_err());
+
+    let mut key_past_end = data.clone();
+    set_u16(&mut key_past_end, 6, (data.len() - 31) as u16);
+    assert!(parse_ed25519_signature(&key_past_end).is_err());
+}
+
+#[test]
+fn test_signature_must_cover_the_attestation() {
+    let provider = Pubkey::new_unique();
+    let attestation = attestation();
+    let data = ed25519_data(&provider, &attestation.message().unwrap());
+    assert_eq!(attestation_signer(&data, &attestation).unwrap(), provider);
+
+    let upgraded = KycAttestation { kyc_level: 3, ..attestation.clone() };
+    assert!(attestation_signer(&data, &upgraded).is_err());
+    let moved = KycAttestation { country: *b"AU", state: *b"NSW", ..attestation.clone() };
+    assert!(attestation_signer(&data, &moved).is_err());
+
+    // same fields signed without the domain separator
+    let mut bare = Vec::new();
+    anchor_lang::AnchorSerialize::serialize(&attestation, &mut bare).unwrap();
+    assert!(attestation_signer(&ed25519_data(&provider, &bare), &attestation).is_err());
+}
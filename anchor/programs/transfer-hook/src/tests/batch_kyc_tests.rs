@@ -0,0 +1,83 @@
+use anchor_lang::{
+    prelude::{ AccountInfo, Pubkey, ProgramError, Result },
+    solana_program::program::MAX_RETURN_DATA,
+    AnchorSerialize,
+};
+
+use crate::{ error::CustomError, instructions::run_batch, state::BatchKycResult };
+
+const BUDGET: u64 = 1_400_000;
+
+fn code(error: CustomError) -> u32 {
+    anchor_lang::error::ERROR_CODE_OFFSET + error as u32
+}
+
+/// Runs `f` over `users` with one throwaway account each
+fn run(users: &[Pubkey], remaining_compute: u64, f: impl FnMut(&Pubkey, &AccountInfo) -> Result<()>) -> Result<Vec<BatchKycResult>> {
+    let keys: Vec<Pubkey> = users.iter().map(|_| Pubkey::new_unique()).collect();
+    let mut lamports = vec![0u64; users.len()];
+    let mut data: Vec<Vec<u8>> = vec![vec![]; users.len()];
+    let owner = Pubkey::default();
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .map(|((key, lamports), data)| AccountInfo::new(key, false, true, lamports, data, &owner, false, 0))
+        .collect();
+    run_batch(users, &accounts, || remaining_compute, |user| *user, f)
+}
+
+#[test]
+fn test_full_batch_fits_return_data() {
+    let results = vec![
+        BatchKycResult { user: Pubkey::new_unique(), error_code: u32::MAX };
+        BatchKycResult::MAX_ENTRIES
+    ];
+    assert!(results.try_to_vec().unwrap().len() <= MAX_RETURN_DATA);
+}
+
+#[test]
+fn test_rule_failures_are_reported_per_entry() {
+    let users = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+    let failing = users[1];
+    let results = run(&users, BUDGET, |user, _| {
+        if *user == failing {
+            return Err(CustomError::InvalidKycLevel.into());
+        }
+        Ok(())
+    }).unwrap();
+    let codes: Vec<u32> = results.iter().map(|r| r.error_code).collect();
+    assert_eq!(codes, [0, code(CustomError::InvalidKycLevel), 0]);
+    assert_eq!(results.iter().map(|r| r.user).collect::<Vec<_>>(), users);
+}
+
+#[test]
+fn test_non_program_error_aborts_batch() {
+    let users = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let mut calls = 0;
+    let result = run(&users, BUDGET, |_, _| {
+        calls += 1;
+        Err(ProgramError::InsufficientFunds.into())
+    });
+    assert!(result.is_err());
+    assert_eq!(calls, 1);
+}
+
+#[test]
+fn test_entries_past_compute_budget_are_skipped() {
+    let users = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let mut calls = 0;
+    let results = run(&users, 0, |_, _| {
+        calls += 1;
+        Ok(())
+    }).unwrap();
+    assert_eq!(calls, 0);
+    assert!(results.iter().all(|r| r.error_code == code(CustomError::BatchComputeExhausted)));
+}
+
+#[test]
+fn test_batch_needs_one_account_per_entry() {
+    let users = [Pubkey::new_unique()];
+    let result = run_batch(&users, &[], || BUDGET, |user| *user, |_, _| Ok(()));
+    assert!(result.is_err());
+}
@@ -0,0 +1,35 @@
+use anchor_lang::prelude::Pubkey;
+
+use crate::state::UserKYC;
+
+#[test]
+fn test_positions_open_and_close_with_their_balance() {
+    let mut user_kyc = UserKYC::default();
+    assert!(user_kyc.record_position_change(0, 10));
+    assert!(user_kyc.record_position_change(0, 5));
+    assert_eq!(user_kyc.open_positions, 2);
+    // moving within an open position changes nothing
+    assert!(!user_kyc.record_position_change(10, 3));
+    assert!(!user_kyc.record_position_change(0, 0));
+    assert_eq!(user_kyc.open_positions, 2);
+    assert!(user_kyc.record_position_change(3, 0));
+    assert!(user_kyc.record_position_change(5, 0));
+    assert_eq!(user_kyc.open_positions, 0);
+}
+
+#[test]
+fn test_unseen_position_never_underflows() {
+    // a balance the hook picked up before the record counted it
+    let mut user_kyc = UserKYC::default();
+    assert!(user_kyc.record_position_change(7, 0));
+    assert_eq!(user_kyc.open_positions, 0);
+}
+
+#[test]
+fn test_recovered_record_reopens_positions_per_mint() {
+    let old = UserKYC { user: Pubkey::new_unique(), open_positions: 3, ..Default::default() };
+    let mut new = old.recovered_to(Pubkey::new_unique(), 42);
+    assert_eq!(new.open_positions, 0);
+    assert!(new.record_position_change(0, 100));
+    assert_eq!(new.open_positions, 1);
+}
@@ -0,0 +1,62 @@
+use anchor_lang::{ prelude::Pubkey, solana_program::program_pack::Pack };
+use anchor_spl::token_2022::spl_token_2022::{
+    extension::{ BaseStateWithExtensionsMut, StateWithExtensionsMut },
+    state::{ Account, Mint },
+};
+use spl_token_metadata_interface::state::{ Field, TokenMetadata };
+
+use crate::state::{ ComplianceMetadata, RwaMetadata, Subdivision, Token2022MetadataParser };
+
+fn subdivision(country: &[u8; 2], code: &str) -> Subdivision {
+    Subdivision { country: *country, code: code.to_string() }
+}
+
+/// Mint account data carrying `metadata` in its own metadata extension
+fn mint_with_metadata(metadata: &TokenMetadata) -> Vec<u8> {
+    // extended mints are padded to the token account length, then carry an account type byte
+    let mut data = vec![0u8; Account::LEN + 1 + metadata.tlv_size_of().unwrap()];
+    let mut mint = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+    mint.init_variable_len_extension(metadata, false).unwrap();
+    mint.base.is_initialized = true;
+    mint.pack_base();
+    mint.init_account_type().unwrap();
+    data
+}
+
+#[test]
+fn test_validate_rejects_unassigned_codes() {
+    assert!(ComplianceMetadata::AllowedCountries(vec![*b"US", *b"DE"]).validate().is_ok());
+    assert!(ComplianceMetadata::AllowedCountries(vec![*b"XX"]).validate().is_err());
+    assert!(ComplianceMetadata::AllowedCountries(vec![*b"us"]).validate().is_err());
+    assert!(ComplianceMetadata::RestrictedStates(vec![subdivision(b"FR", "971")]).validate().is_ok());
+    assert!(ComplianceMetadata::RestrictedStates(vec![subdivision(b"US", "CALI")]).validate().is_err());
+    assert!(ComplianceMetadata::RestrictedStates(vec![subdivision(b"ZZ", "CA")]).validate().is_err());
+}
+
+#[test]
+fn test_empty_values_remove_fields() {
+    assert_eq!(ComplianceMetadata::AllowedCountries(vec![]).fields(), vec![(RwaMetadata::ALLOWED_COUNTRIES, None)]);
+    assert_eq!(ComplianceMetadata::AllowedCountryGroups(0).fields()[0].1, None);
+}
+
+#[test]
+fn test_written_fields_parse_back_from_mint() {
+    let fields = [
+        ComplianceMetadata::AllowedCountries(vec![*b"US", *b"DE"]),
+        ComplianceMetadata::RestrictedStates(vec![subdivision(b"US", "CA"), subdivision(b"FR", "971")]),
+    ];
+    let mut metadata = TokenMetadata { mint: Pubkey::new_unique(), name: "Bond".into(), ..Default::default() };
+    for (key, value) in fields.iter().flat_map(ComplianceMetadata::fields) {
+        metadata.update(Field::Key(key.to_string()), value.unwrap());
+    }
+
+    let parsed = Token2022MetadataParser::parse_metadata_from_mint(&mint_with_metadata(&metadata)).unwrap();
+    let rwa = Token2022MetadataParser::extract_rwa_metadata(&parsed);
+    let allowed = rwa.allowed_countries.unwrap();
+    assert!(RwaMetadata::list_contains(&allowed, "DE"));
+    assert!(!RwaMetadata::list_contains(&allowed, "S"));
+    let restricted = rwa.restricted_states.unwrap();
+    assert!(RwaMetadata::list_contains(&restricted, "FR_971"));
+    assert!(!RwaMetadata::list_contains(&restricted, "US_C"));
+    assert!(rwa.allowed_country_groups.is_none());
+}
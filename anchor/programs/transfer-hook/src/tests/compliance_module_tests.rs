@@ -0,0 +1,119 @@
+use anchor_lang::AnchorSerialize;
+
+use crate::state::{
+    ComplianceModule,
+    CountryRoute,
+    HolderPosition,
+    Jurisdiction,
+    MintPolicy,
+    SessionWindow,
+    UserKYC,
+    VelocityLimits,
+};
+
+fn all_modules() -> Vec<ComplianceModule> {
+    vec![
+        ComplianceModule::KycLevel { min_level: UserKYC::ENHANCED },
+        ComplianceModule::Geography { allowed_country_groups: 1 },
+        ComplianceModule::Volume { daily_limit: 1, monthly_limit: 2 },
+        ComplianceModule::HoldingPeriod { lockup_periods: [1, 2, 3, 4] },
+        ComplianceModule::MaxHolders { max_holders: 99, max_holder_bps: 500 },
+        ComplianceModule::InvestorClass { allowed_classes: 0b0110 },
+        ComplianceModule::Whitelist,
+        ComplianceModule::InvestorRequirements { required_flags: UserKYC::INVESTOR_ACCREDITED },
+        ComplianceModule::PoliticallyExposed { min_level: UserKYC::ENHANCED, daily_limit: 10, monthly_limit: 20 },
+        ComplianceModule::RiskBands { limit_from: 40, max_amount: 1_000, approval_from: 70, block_from: 90 },
+        ComplianceModule::CrossBorder {
+            routes: vec![
+                CountryRoute { sender: Jurisdiction::Country(*b"DE"), receiver: Jurisdiction::Groups(1 << 3) },
+                CountryRoute { sender: Jurisdiction::Any, receiver: Jurisdiction::Country(*b"US") }
+            ],
+        },
+        ComplianceModule::TradingSessions {
+            utc_offset_minutes: -300,
+            windows: vec![SessionWindow { days: 0b0011111, open_minute: 570, close_minute: 960 }],
+        },
+        ComplianceModule::TravelRule { threshold: 1_000 },
+        ComplianceModule::Velocity {
+            reporting_threshold: 10_000,
+            near_threshold_bps: 9_000,
+            review: VelocityLimits { daily_transfers: 20, daily_near_threshold: 2, monthly_near_threshold: 5 },
+            block: VelocityLimits { daily_transfers: 50, daily_near_threshold: 4, monthly_near_threshold: 10 },
+            risk_increase: 25,
+        },
+        ComplianceModule::DelegateKyc { min_level: UserKYC::BASIC }
+    ]
+}
+
+#[test]
+fn test_space_matches_serialized_policy() {
+    let modules = all_modules();
+    let policy = MintPolicy {
+        mint: Default::default(),
+        authority: Default::default(),
+        bump: 0,
+        modules,
+        version: 1,
+        resolves_identities: false,
+    };
+    assert_eq!(MintPolicy::space(&policy.modules), 8 + policy.try_to_vec().unwrap().len());
+}
+
+#[test]
+fn test_validate_modules() {
+    assert!(MintPolicy::validate_modules(&all_modules()).is_ok());
+    assert!(MintPolicy::validate_modules(&ComplianceModule::default_chain()).is_ok());
+    let duplicate = vec![ComplianceModule::Whitelist, ComplianceModule::Whitelist];
+    assert!(MintPolicy::validate_modules(&duplicate).is_err());
+    assert!(ComplianceModule::KycLevel { min_level: UserKYC::UNVERIFIED }.validate().is_err());
+    assert!(ComplianceModule::HoldingPeriod { lockup_periods: [0, -1, 0, 0] }.validate().is_err());
+    assert!(ComplianceModule::MaxHolders { max_holders: 0, max_holder_bps: 10_001 }.validate().is_err());
+    assert!(ComplianceModule::InvestorClass { allowed_classes: 0 }.validate().is_err());
+    assert!(ComplianceModule::InvestorRequirements { required_flags: 0 }.validate().is_err());
+    let both = UserKYC::INVESTOR_US_PERSON | UserKYC::INVESTOR_NON_US_PERSON;
+    assert!(ComplianceModule::InvestorRequirements { required_flags: both }.validate().is_err());
+    assert!(ComplianceModule::TravelRule { threshold: 0 }.validate().is_err());
+    assert!(ComplianceModule::DelegateKyc { min_level: UserKYC::UNVERIFIED }.validate().is_err());
+}
+
+#[test]
+fn test_volume_limits_roll_over() {
+    let day = 86400;
+    let mut position = HolderPosition::default();
+    assert!(position.record_volume(60, 0, 100, 150).is_ok());
+    assert!(position.record_volume(41, 10, 100, 150).is_err());
+    let mut position = HolderPosition::default();
+    assert!(position.record_volume(60, 0, 100, 150).is_ok());
+    assert!(position.record_volume(60, day, 100, 150).is_ok());
+    assert!(position.record_volume(60, 2 * day, 100, 150).is_err());
+}
+
+#[test]
+fn test_volume_counters_are_per_mint() {
+    // the same holder's positions in two mints fill their limits independently
+    let mut first = HolderPosition::default();
+    let mut second = HolderPosition::default();
+    assert!(first.record_volume(100, 0, 100, 0).is_ok());
+    assert!(second.record_volume(100, 0, 100, 0).is_ok());
+    assert!(first.record_volume(1, 0, 100, 0).is_err());
+}
+
+#[test]
+fn test_set_module_keeps_chain_order() {
+    let mut policy = MintPolicy {
+        mint: Default::default(),
+        authority: Default::default(),
+        bump: 0,
+        modules: ComplianceModule::default_chain(),
+        version: 0,
+        resolves_identities: false,
+    };
+    policy.set_module(ComplianceModule::Geography { allowed_country_groups: 1 });
+    assert_eq!(policy.modules[1], ComplianceModule::Geography { allowed_country_groups: 1 });
+    assert_eq!(policy.modules.len(), 3);
+    policy.set_module(ComplianceModule::Whitelist);
+    assert_eq!(policy.modules.last(), Some(&ComplianceModule::Whitelist));
+    policy.remove_module(&ComplianceModule::Geography { allowed_country_groups: 0 });
+    assert_eq!(policy.modules.len(), 3);
+    assert!(!policy.modules.iter().any(|m| matches!(m, ComplianceModule::Geography { .. })));
+}
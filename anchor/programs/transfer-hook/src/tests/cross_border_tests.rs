@@ -0,0 +1,43 @@
+use crate::state::{ ComplianceModule, CountryGroups, CountryRoute, Jurisdiction, UserKYC };
+
+fn holder(country: &str) -> UserKYC {
+    let mut user_kyc = UserKYC::default();
+    user_kyc.set_country(country);
+    user_kyc
+}
+
+fn allowed(routes: &[CountryRoute], sender: &UserKYC, receiver: &UserKYC) -> bool {
+    routes.iter().any(|r| r.allows(sender, receiver))
+}
+
+#[test]
+fn test_routes_are_directional() {
+    let eu = Jurisdiction::Groups(CountryGroups::EU);
+    let routes = vec![
+        CountryRoute { sender: eu.clone(), receiver: eu.clone() },
+        CountryRoute { sender: Jurisdiction::Country(*b"US"), receiver: eu }
+    ];
+    assert!(allowed(&routes, &holder("FR"), &holder("DE")));
+    assert!(allowed(&routes, &holder("US"), &holder("DE")));
+    assert!(!allowed(&routes, &holder("DE"), &holder("US")));
+    assert!(!allowed(&routes, &holder("US"), &holder("US")));
+}
+
+#[test]
+fn test_private_records_match_groups_only() {
+    let mut private = holder("FR");
+    private.make_private([1; 32], CountryGroups::EU);
+    assert!(Jurisdiction::Groups(CountryGroups::EU).contains(&private));
+    assert!(!Jurisdiction::Country(*b"FR").contains(&private));
+    assert!(Jurisdiction::Any.contains(&private));
+}
+
+#[test]
+fn test_validate_routes() {
+    assert!(ComplianceModule::CrossBorder { routes: vec![] }.validate().is_err());
+    let route = |sender| CountryRoute { sender, receiver: Jurisdiction::Any };
+    assert!(ComplianceModule::CrossBorder { routes: vec![route(Jurisdiction::Country(*b"fr"))] }.validate().is_err());
+    assert!(ComplianceModule::CrossBorder { routes: vec![route(Jurisdiction::Groups(0))] }.validate().is_err());
+    let too_many = vec![route(Jurisdiction::Any); CountryRoute::MAX_ROUTES + 1];
+    assert!(ComplianceModule::CrossBorder { routes: too_many }.validate().is_err());
+}
@@ -0,0 +1,32 @@
+use anchor_lang::prelude::Pubkey;
+
+use crate::instructions::extra_account_metas;
+
+/// Resolves fixed meta `index` for a transfer signed by `authority` from a token account owned by `owner`
+fn resolve(index: usize, owner: &Pubkey, authority: &Pubkey) -> Pubkey {
+    let mut source_token = vec![0u8; 165];
+    source_token[32..64].copy_from_slice(owner.as_ref());
+    let source_key = Pubkey::new_unique();
+    let metas = extra_account_metas().unwrap();
+    let meta = metas[index - 5]
+        .resolve(&[], &crate::ID, |i| match i {
+            0 => Some((&source_key, Some(source_token.as_slice()))),
+            3 => Some((authority, None)),
+            _ => None,
+        })
+        .unwrap();
+    meta.pubkey
+}
+
+fn user_kyc(user: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"user-kyc", user.as_ref()], &crate::ID).0
+}
+
+#[test]
+fn test_kyc_follows_token_owner_not_signer() {
+    let (owner, delegate) = (Pubkey::new_unique(), Pubkey::new_unique());
+    assert_eq!(resolve(5, &owner, &delegate), user_kyc(&owner));
+    assert_eq!(resolve(20, &owner, &delegate), owner);
+    assert_eq!(resolve(21, &owner, &delegate), user_kyc(&delegate));
+    assert_eq!(resolve(21, &owner, &owner), resolve(5, &owner, &owner));
+}
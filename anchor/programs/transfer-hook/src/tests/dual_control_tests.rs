@@ -0,0 +1,63 @@
+use crate::state::{ ComplianceConfig, PendingKycChange, UserKYC };
+
+fn config(dual_control_level: u8) -> ComplianceConfig {
+    ComplianceConfig {
+        authority: Default::default(),
+        officers: vec![],
+        kyc_validity: ComplianceConfig::DEFAULT_KYC_VALIDITY,
+        grace_period: 0,
+        bump: 0,
+        dual_control_level,
+        pending_change_ttl: 0,
+    }
+}
+
+#[test]
+fn test_lifting_restrictions_needs_dual_control() {
+    let config = config(UserKYC::INSTITUTIONAL);
+    let user_kyc = UserKYC { kyc_level: UserKYC::BASIC, flags: UserKYC::FLAG_SANCTIONS, ..Default::default() };
+    assert!(config.requires_dual_control(&user_kyc, None, UserKYC::FLAG_SANCTIONS));
+    assert!(config.requires_dual_control(&user_kyc, None, 0xff));
+    // clearing a flag that is not set, or one outside dual control, is not sensitive
+    assert!(!config.requires_dual_control(&user_kyc, None, UserKYC::FLAG_FROZEN));
+    assert!(!config.requires_dual_control(&user_kyc, None, UserKYC::FLAG_PEP));
+}
+
+#[test]
+fn test_level_changes_from_threshold() {
+    let every_change = config(0);
+    let config = config(UserKYC::ENHANCED);
+    let basic = UserKYC { kyc_level: UserKYC::BASIC, ..Default::default() };
+    let enhanced = UserKYC { kyc_level: UserKYC::ENHANCED, ..Default::default() };
+    assert!(!config.requires_dual_control(&basic, Some(UserKYC::UNVERIFIED), 0));
+    assert!(config.requires_dual_control(&basic, Some(UserKYC::ENHANCED), 0));
+    assert!(config.requires_dual_control(&enhanced, Some(UserKYC::BASIC), 0));
+    assert!(!config.requires_dual_control(&enhanced, Some(UserKYC::ENHANCED), 0));
+    // the zero default puts every level change under dual control
+    assert!(every_change.requires_dual_control(&basic, Some(UserKYC::UNVERIFIED), 0));
+}
+
+#[test]
+fn test_apply_pending_change() {
+    let config = config(0);
+    let mut user_kyc = UserKYC {
+        kyc_level: UserKYC::BASIC,
+        flags: UserKYC::FLAG_SANCTIONS | UserKYC::FLAG_PEP,
+        ..Default::default()
+    };
+    let change = PendingKycChange {
+        user: Default::default(),
+        kyc_level: Some(UserKYC::ENHANCED),
+        flags_to_set: UserKYC::FLAG_REVIEW,
+        flags_to_clear: UserKYC::FLAG_SANCTIONS,
+        proposed_by: Default::default(),
+        proposed_at: 0,
+        expires_at: 10,
+        bump: 0,
+    };
+    change.apply(&mut user_kyc, &config, 5);
+    assert_eq!(user_kyc.kyc_level, UserKYC::ENHANCED);
+    assert_eq!(user_kyc.flags, UserKYC::FLAG_PEP | UserKYC::FLAG_REVIEW);
+    assert_eq!(user_kyc.verified_at, 5);
+    assert_eq!(user_kyc.expires_at, 5 + config.validity_period(UserKYC::ENHANCED));
+}
@@ -0,0 +1,42 @@
+use crate::state::{ HolderPosition, HolderRegistry };
+
+#[test]
+fn test_new_holder_beyond_cap_is_rejected() {
+    let mut registry = HolderRegistry { holder_count: 2, ..Default::default() };
+    assert!(registry.apply_transfer(false, true, 2).is_err());
+    assert!(registry.apply_transfer(false, false, 2).is_ok());
+    assert_eq!(registry.holder_count, 2);
+    assert!(registry.apply_transfer(false, true, 0).is_ok());
+    assert_eq!(registry.holder_count, 3);
+}
+
+#[test]
+fn test_exiting_holder_frees_slot_for_destination() {
+    let mut registry = HolderRegistry { holder_count: 2, ..Default::default() };
+    assert!(registry.apply_transfer(true, true, 2).is_ok());
+    assert_eq!(registry.holder_count, 2);
+}
+
+#[test]
+fn test_ownership_cap_against_supply() {
+    assert!(HolderRegistry::check_ownership(100, 1_000, 1_000).is_ok());
+    assert!(HolderRegistry::check_ownership(101, 1_000, 1_000).is_err());
+    assert!(HolderRegistry::check_ownership(u64::MAX, 1, 0).is_ok());
+}
+
+#[test]
+fn test_holder_count_cannot_overflow() {
+    let mut registry = HolderRegistry { holder_count: u32::MAX, ..Default::default() };
+    assert!(registry.apply_transfer(false, true, 0).is_err());
+    assert_eq!(registry.holder_count, u32::MAX);
+}
+
+#[test]
+fn test_position_takes_up_untracked_balance() {
+    let mut position = HolderPosition::default();
+    position.reconcile(100);
+    assert_eq!(position.balance, 100);
+    // a second account holding less does not lower the position
+    position.reconcile(40);
+    assert_eq!(position.balance, 100);
+}
@@ -0,0 +1,24 @@
+use crate::state::{ HolderStatus, UserKYC };
+
+#[test]
+fn test_frozen_for_mint_only_blocks_that_status() {
+    let status = HolderStatus { flags: HolderStatus::FLAG_FROZEN | HolderStatus::FLAG_WHITELISTED, ..Default::default() };
+    assert!(status.check().is_err());
+    assert!(HolderStatus::default().check().is_ok());
+}
+
+#[test]
+fn test_investor_class_override() {
+    let user_kyc = UserKYC { investor_class: UserKYC::CLASS_PROFESSIONAL, ..Default::default() };
+    assert_eq!(HolderStatus::default().investor_class(&user_kyc), UserKYC::CLASS_PROFESSIONAL);
+    let status = HolderStatus { investor_class: Some(UserKYC::CLASS_AFFILIATE), ..Default::default() };
+    assert_eq!(status.investor_class(&user_kyc), UserKYC::CLASS_AFFILIATE);
+}
+
+#[test]
+fn test_unknown_status_flags_are_rejected() {
+    assert!(HolderStatus::validate_flags(HolderStatus::FLAGS_MASK).is_ok());
+    assert!(HolderStatus::validate_flags(0).is_ok());
+    assert!(HolderStatus::validate_flags(0x08).is_err());
+    assert!(HolderStatus::validate_flags(HolderStatus::FLAG_FROZEN | 0x80).is_err());
+}
@@ -0,0 +1,93 @@
+use crate::state::{ HoldingLots, Lot };
+
+const DAY: i64 = 86400;
+
+#[test]
+fn test_locked_lot_blocks_transfer() {
+    let mut lots = HoldingLots::default();
+    lots.record(100, 0, 30 * DAY).unwrap();
+    assert!(lots.release(100, 1, 30 * DAY, 30 * DAY - 1).is_err());
+    assert!(lots.release(100, 100, 30 * DAY, 30 * DAY).is_ok());
+    assert!(lots.lots.is_empty());
+}
+
+#[test]
+fn test_untracked_balance_is_spent_first() {
+    let mut lots = HoldingLots::default();
+    lots.record(40, 0, 30 * DAY).unwrap();
+    // 60 held before tracking started
+    assert!(lots.release(100, 61, 30 * DAY, DAY).is_err());
+    assert!(lots.release(100, 60, 30 * DAY, DAY).is_ok());
+    assert_eq!(lots.lots, vec![Lot { amount: 40, acquired_at: 0 }]);
+}
+
+#[test]
+fn test_balance_across_accounts_leaves_locked_lots() {
+    let mut lots = HoldingLots::default();
+    lots.record(100, 0, 30 * DAY).unwrap();
+    // the holder's position also covers 50 held before tracking in another account
+    assert!(lots.release(150, 51, 30 * DAY, DAY).is_err());
+    assert!(lots.release(150, 50, 30 * DAY, DAY).is_ok());
+    assert_eq!(lots.lots, vec![Lot { amount: 100, acquired_at: 0 }]);
+}
+
+#[test]
+fn test_release_consumes_oldest_lots() {
+    let mut lots = HoldingLots::default();
+    lots.record(50, 0, 30 * DAY).unwrap();
+    lots.record(50, 10 * DAY, 30 * DAY).unwrap();
+    assert!(lots.release(100, 70, 5 * DAY, 11 * DAY).is_err());
+    assert!(lots.release(100, 70, 5 * DAY, 15 * DAY).is_ok());
+    assert_eq!(lots.lots, vec![Lot { amount: 30, acquired_at: 10 * DAY }]);
+}
+
+#[test]
+fn test_lots_beyond_balance_are_dropped() {
+    let mut lots = HoldingLots::default();
+    lots.record(50, 0, 30 * DAY).unwrap();
+    lots.record(50, 10 * DAY, 30 * DAY).unwrap();
+    // 40 burned outside the hook, taken from the oldest lot
+    assert!(lots.release(60, 10, 30 * DAY, 31 * DAY).is_ok());
+    assert_eq!(lots.lots, vec![Lot { amount: 50, acquired_at: 10 * DAY }]);
+}
+
+#[test]
+fn test_full_lots_reject_new_lot() {
+    let mut lots = HoldingLots::default();
+    for i in 0..HoldingLots::MAX_LOTS as i64 {
+        lots.record(1, i, 30 * DAY).unwrap();
+    }
+    // dust cannot push the newest lot's lockup out
+    assert!(lots.record(1, 100, 30 * DAY).is_err());
+    assert_eq!(lots.lots.last(), Some(&(Lot { amount: 1, acquired_at: HoldingLots::MAX_LOTS as i64 - 1 })));
+    // same second still merges
+    assert!(lots.record(1, HoldingLots::MAX_LOTS as i64 - 1, 30 * DAY).is_ok());
+}
+
+#[test]
+fn test_unlocked_lots_make_room() {
+    let mut lots = HoldingLots::default();
+    for i in 0..HoldingLots::MAX_LOTS as i64 {
+        lots.record(1, i * DAY, 30 * DAY).unwrap();
+    }
+    assert!(lots.record(5, 31 * DAY, 30 * DAY).is_ok());
+    assert_eq!(lots.lots.len(), HoldingLots::MAX_LOTS - 1);
+    assert_eq!(lots.lots[0], Lot { amount: 1, acquired_at: 2 * DAY });
+    // the dropped lots count as untracked, so unlocked
+    assert!(lots.release(21, 2, 30 * DAY, 31 * DAY).is_ok());
+}
+
+#[test]
+fn test_absorb_keeps_acquisition_order() {
+    let mut lots = HoldingLots::default();
+    lots.record(10, 5 * DAY, 30 * DAY).unwrap();
+    lots.absorb(&[Lot { amount: 20, acquired_at: 0 }, Lot { amount: 30, acquired_at: 9 * DAY }]);
+    assert_eq!(
+        lots.lots,
+        vec![
+            Lot { amount: 20, acquired_at: 0 },
+            Lot { amount: 10, acquired_at: 5 * DAY },
+            Lot { amount: 30, acquired_at: 9 * DAY }
+        ]
+    );
+}
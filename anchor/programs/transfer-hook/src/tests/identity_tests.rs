@@ -0,0 +1,55 @@
+use anchor_lang::{ prelude::Pubkey, AccountSerialize };
+
+use crate::{ instructions::{ extra_account_metas, identity_account_metas }, state::WalletLink };
+
+#[test]
+fn test_identity_metas_follow_fixed_metas() {
+    let fixed = extra_account_metas().unwrap().len();
+    assert_eq!(5 + fixed, 24);
+    assert_eq!(identity_account_metas().unwrap().len(), 6);
+}
+
+#[test]
+fn test_identity_resolves_from_wallet_link() {
+    let identity = Pubkey::new_unique();
+    let link = WalletLink { identity, wallet: Pubkey::new_unique(), ..Default::default() };
+    let mut data = Vec::new();
+    link.try_serialize(&mut data).unwrap();
+
+    // only the link at [24] is read when resolving the source identity at [26]
+    let link_key = Pubkey::new_unique();
+    let source_identity = &identity_account_metas().unwrap()[2];
+    let meta = source_identity
+        .resolve(&[], &crate::ID, |index| (index == 24).then_some((&link_key, Some(data.as_slice()))))
+        .unwrap();
+    let (expected, _) = Pubkey::find_program_address(&[b"user-kyc", identity.as_ref()], &crate::ID);
+    assert_eq!(meta.pubkey, expected);
+    assert!(meta.is_writable);
+
+    let unlinked = source_identity.resolve(&[], &crate::ID, |index| {
+        (index == 24).then_some((&link_key, None))
+    });
+    assert!(unlinked.is_err());
+}
+
+#[test]
+fn test_identity_position_follows_wallet_link() {
+    let identity = Pubkey::new_unique();
+    let link = WalletLink { identity, wallet: Pubkey::new_unique(), ..Default::default() };
+    let mut data = Vec::new();
+    link.try_serialize(&mut data).unwrap();
+
+    // [29] destination_identity_position reads the mint at [1] and the link at [25]
+    let (mint, link_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let destination_position = &identity_account_metas().unwrap()[5];
+    let meta = destination_position
+        .resolve(&[], &crate::ID, |index| match index {
+            1 => Some((&mint, None)),
+            25 => Some((&link_key, Some(data.as_slice()))),
+            _ => None,
+        })
+        .unwrap();
+    let seeds = [b"holder-position".as_ref(), mint.as_ref(), identity.as_ref()];
+    assert_eq!(meta.pubkey, Pubkey::find_program_address(&seeds, &crate::ID).0);
+    assert!(meta.is_writable);
+}
@@ -0,0 +1,28 @@
+use crate::state::{ HolderPosition, UserKYC };
+
+#[test]
+fn test_validate_investor_flags() {
+    assert!(UserKYC::validate_investor_flags(0).is_ok());
+    assert!(UserKYC::validate_investor_flags(UserKYC::INVESTOR_ACCREDITED | UserKYC::INVESTOR_US_PERSON).is_ok());
+    assert!(UserKYC::validate_investor_flags(UserKYC::INVESTOR_US_PERSON | UserKYC::INVESTOR_NON_US_PERSON).is_err());
+    assert!(UserKYC::validate_investor_flags(0x10).is_err());
+}
+
+#[test]
+fn test_unclassified_holder_meets_no_requirement() {
+    let mut user_kyc = UserKYC::default();
+    assert!(!user_kyc.has_investor_flags(UserKYC::INVESTOR_NON_US_PERSON));
+    assert!(!user_kyc.has_investor_flags(UserKYC::INVESTOR_ACCREDITED));
+    user_kyc.investor_flags = UserKYC::INVESTOR_ACCREDITED | UserKYC::INVESTOR_NON_US_PERSON;
+    assert!(user_kyc.has_investor_flags(UserKYC::INVESTOR_NON_US_PERSON));
+    assert!(!user_kyc.has_investor_flags(UserKYC::INVESTOR_ACCREDITED | UserKYC::INVESTOR_QUALIFIED_PURCHASER));
+}
+
+#[test]
+fn test_second_volume_limit_checks_recorded_counters() {
+    let mut position = HolderPosition::default();
+    assert!(position.record_volume(60, 0, 100, 0).is_ok());
+    assert!(position.check_volume(50, 0).is_err());
+    assert!(position.check_volume(60, 0).is_ok());
+    assert_eq!(position.daily_volume, 60);
+}
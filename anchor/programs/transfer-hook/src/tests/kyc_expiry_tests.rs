@@ -0,0 +1,73 @@
+use crate::state::UserKYC;
+
+const DAY: i64 = 86400;
+
+fn verified_user(now: i64, validity_period: i64) -> UserKYC {
+    let mut user_kyc = UserKYC { kyc_level: UserKYC::BASIC, ..Default::default() };
+    user_kyc.verify(now, validity_period);
+    user_kyc
+}
+
+#[test]
+fn test_verification_without_validity_never_expires() {
+    let user_kyc = verified_user(1_000, 0);
+    assert_eq!(user_kyc.expires_at, 0);
+    assert!(!user_kyc.is_expired(i64::MAX));
+    assert!(user_kyc.is_eligible_for_trading(i64::MAX, 0, false));
+}
+
+#[test]
+fn test_expires_at_validity_end() {
+    let user_kyc = verified_user(1_000, 30 * DAY);
+    assert!(!user_kyc.is_expired(1_000 + 30 * DAY - 1));
+    assert!(user_kyc.is_expired(1_000 + 30 * DAY));
+}
+
+#[test]
+fn test_grace_period_allows_outgoing_only() {
+    let user_kyc = verified_user(0, 30 * DAY);
+    let now = 31 * DAY;
+    assert!(user_kyc.in_grace_period(now, 7 * DAY));
+    assert!(user_kyc.is_eligible_for_trading(now, 7 * DAY, true));
+    assert!(!user_kyc.is_eligible_for_trading(now, 7 * DAY, false));
+    assert!(!user_kyc.is_eligible_for_trading(now, 0, true));
+    assert!(!user_kyc.is_eligible_for_trading(37 * DAY, 7 * DAY, true));
+}
+
+#[test]
+fn test_expired_flag_has_no_grace_period() {
+    let mut user_kyc = verified_user(0, 30 * DAY);
+    user_kyc.flags |= UserKYC::FLAG_EXPIRED;
+    assert!(user_kyc.is_expired(DAY));
+    assert!(!user_kyc.in_grace_period(31 * DAY, 7 * DAY));
+    assert!(!user_kyc.is_eligible_for_trading(DAY, 7 * DAY, true));
+}
+
+#[test]
+fn test_renewal_clears_expired_flag() {
+    let mut user_kyc = verified_user(0, 30 * DAY);
+    user_kyc.flags |= UserKYC::FLAG_EXPIRED;
+    user_kyc.verify(40 * DAY, 30 * DAY);
+    assert_eq!(user_kyc.verified_at, 40 * DAY);
+    assert_eq!(user_kyc.expires_at, 70 * DAY);
+    assert!(user_kyc.is_eligible_for_trading(41 * DAY, 0, false));
+}
+
+#[test]
+fn test_baseline_record_loads_without_migration() {
+    use anchor_lang::{ prelude::*, Discriminator };
+
+    let key = Pubkey::new_unique();
+    let user = Pubkey::new_unique();
+    let record = UserKYC { user, kyc_level: UserKYC::BASIC, state: *b"NY", ..Default::default() };
+    let mut data = UserKYC::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&record.try_to_vec().unwrap());
+    // baseline records end at `city`, before `verified_at`
+    data.truncate(8 + 32 + 1 + 1 + 8 + 1 + 8 + 8 + 8 + 8 + 2 + 2 + 32);
+    let mut lamports = 0;
+    let ai = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &crate::ID, false, 0);
+    let loaded = crate::instructions::load_user_kyc(&ai, &user).unwrap();
+    assert_eq!(loaded.expires_at, 0);
+    assert_eq!(loaded.get_state_str(), "NY");
+    assert!(loaded.is_eligible_for_trading(i64::MAX, 0, false));
+}
@@ -0,0 +1,28 @@
+use crate::state::{ CountryGroups, UserKYC };
+
+#[test]
+fn test_country_groups() {
+    assert_eq!(CountryGroups::of(b"US"), CountryGroups::US);
+    assert_eq!(CountryGroups::of(b"FR"), CountryGroups::EU);
+    assert_eq!(CountryGroups::of(b"CH"), CountryGroups::EFTA);
+    assert_eq!(CountryGroups::of(b"GB"), CountryGroups::UK);
+    assert_eq!(CountryGroups::of(b"JP"), CountryGroups::OTHER);
+}
+
+#[test]
+fn test_make_private_clears_location() {
+    let mut user_kyc = UserKYC::default();
+    user_kyc.set_country("DE");
+    user_kyc.set_state("BY");
+    user_kyc.set_city("Munich");
+    assert_eq!(user_kyc.country_groups(), CountryGroups::EU);
+
+    user_kyc.make_private([7; 32], CountryGroups::EU | CountryGroups::EFTA);
+    assert!(user_kyc.is_private);
+    assert_eq!(user_kyc.country, [0; 2]);
+    assert_eq!(user_kyc.state, [0; 2]);
+    assert_eq!(user_kyc.subdivision, [0; 3]);
+    assert_eq!(user_kyc.city, [0; 32]);
+    assert_eq!(user_kyc.identity_hash, [7; 32]);
+    assert_eq!(user_kyc.country_groups(), CountryGroups::EU | CountryGroups::EFTA);
+}
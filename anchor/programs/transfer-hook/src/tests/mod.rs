@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod kyc_expiry_tests;
+
+#[cfg(test)]
+mod kyc_privacy_tests;
+
+#[cfg(test)]
+mod holding_lots_tests;
+
+#[cfg(test)]
+mod holder_registry_tests;
+
+#[cfg(test)]
+mod holder_status_tests;
+
+#[cfg(test)]
+mod compliance_module_tests;
+
+#[cfg(test)]
+mod investor_flags_tests;
+
+#[cfg(test)]
+mod risk_band_tests;
+
+#[cfg(test)]
+mod cross_border_tests;
+
+#[cfg(test)]
+mod trading_session_tests;
+
+#[cfg(test)]
+mod travel_rule_tests;
+
+#[cfg(test)]
+mod transfer_journal_tests;
+
+#[cfg(test)]
+mod velocity_tests;
+
+#[cfg(test)]
+mod dual_control_tests;
+
+#[cfg(test)]
+mod batch_kyc_tests;
+
+#[cfg(test)]
+mod identity_tests;
+
+#[cfg(test)]
+mod delegate_kyc_tests;
+
+#[cfg(test)]
+mod compliance_metadata_tests;
+
+#[cfg(test)]
+mod subdivision_tests;
+
+#[cfg(test)]
+mod attestation_tests;
+
+#[cfg(test)]
+mod transfer_hook_tests;
+
+#[cfg(test)]
+mod recovery_tests;
+
+#[cfg(test)]
+mod close_user_kyc_tests;
@@ -0,0 +1,76 @@
+use anchor_lang::prelude::Pubkey;
+
+use crate::state::{ HolderPosition, HolderRegistry, RecoveryRequest, UserKYC };
+
+fn request(approved_by: Option<Pubkey>, expires_at: i64) -> RecoveryRequest {
+    RecoveryRequest {
+        mint: Pubkey::new_unique(),
+        old_owner: Pubkey::new_unique(),
+        new_owner: Pubkey::new_unique(),
+        proposed_by: Pubkey::new_unique(),
+        approved_by,
+        proposed_at: 0,
+        bump: 0,
+        expires_at,
+    }
+}
+
+#[test]
+fn test_execution_needs_approval_before_expiry() {
+    let approver = Pubkey::new_unique();
+    assert!(request(None, 100).approval(10).is_err());
+    assert_eq!(request(Some(approver), 100).approval(99).unwrap(), approver);
+    assert!(request(Some(approver), 100).approval(100).is_err());
+}
+
+#[test]
+fn test_whole_balance_moves_holder_slot() {
+    let mut registry = HolderRegistry { holder_count: 2, ..Default::default() };
+    let mut old = HolderPosition { balance: 100, ..Default::default() };
+    let mut new = HolderPosition::default();
+    let (exits, joins) = HolderPosition::move_balance(&mut old, &mut new, 100);
+    assert!(exits && joins);
+    // a full cap does not block the same owner under a new key
+    assert!(registry.apply_transfer(exits, joins, 0).is_ok());
+    assert_eq!(registry.holder_count, 2);
+    assert_eq!((old.balance, new.balance), (0, 100));
+}
+
+#[test]
+fn test_recovery_into_existing_holder_frees_slot() {
+    let mut registry = HolderRegistry { holder_count: 2, ..Default::default() };
+    let mut old = HolderPosition { balance: 30, ..Default::default() };
+    let mut new = HolderPosition { balance: 70, ..Default::default() };
+    let (exits, joins) = HolderPosition::move_balance(&mut old, &mut new, 30);
+    assert!(exits && !joins);
+    registry.apply_transfer(exits, joins, 0).unwrap();
+    assert_eq!(registry.holder_count, 1);
+    assert_eq!(new.balance, 100);
+}
+
+#[test]
+fn test_partial_position_keeps_old_holder() {
+    // the lost wallet holds more in accounts the recovery does not touch
+    let mut old = HolderPosition { balance: 100, ..Default::default() };
+    let mut new = HolderPosition::default();
+    assert_eq!(HolderPosition::move_balance(&mut old, &mut new, 40), (false, true));
+    assert_eq!(old.balance, 60);
+}
+
+#[test]
+fn test_kyc_record_carries_over() {
+    let old_owner = Pubkey::new_unique();
+    let new_owner = Pubkey::new_unique();
+    let old = UserKYC {
+        user: old_owner,
+        kyc_level: UserKYC::BASIC,
+        expires_at: 500,
+        flags: UserKYC::FLAG_FROZEN | UserKYC::FLAG_PEP,
+        ..Default::default()
+    };
+    let new = old.recovered_to(new_owner, 42);
+    assert_eq!(new.user, new_owner);
+    assert_eq!(new.last_updated, 42);
+    assert_eq!((new.kyc_level, new.expires_at), (UserKYC::BASIC, 500));
+    assert_eq!(new.flags, UserKYC::FLAG_PEP);
+}
@@ -0,0 +1,38 @@
+use crate::state::{ ComplianceModule, TransferApproval, UserKYC };
+
+fn bands(limit_from: u8, approval_from: u8, block_from: u8) -> ComplianceModule {
+    ComplianceModule::RiskBands { limit_from, max_amount: 1_000, approval_from, block_from }
+}
+
+#[test]
+fn test_risk_bands_must_ascend() {
+    assert!(bands(40, 70, 90).validate().is_ok());
+    assert!(bands(40, 40, 40).validate().is_ok());
+    assert!(bands(0, 101, 101).validate().is_ok());
+    assert!(bands(70, 40, 90).validate().is_err());
+    assert!(bands(40, 90, 70).validate().is_err());
+    assert!(bands(40, 70, UserKYC::MAX_RISK_SCORE + 2).validate().is_err());
+}
+
+#[test]
+fn test_transfer_approval_draws_down() {
+    let mut approval = TransferApproval { remaining_amount: 100, expires_at: 50, ..Default::default() };
+    assert!(approval.draw(60, 10).is_ok());
+    assert!(approval.draw(41, 10).is_err());
+    assert_eq!(approval.remaining_amount, 40);
+    assert!(approval.draw(40, 50).is_err());
+    assert!(approval.draw(40, 49).is_ok());
+    assert_eq!(approval.remaining_amount, 0);
+}
+
+#[test]
+fn test_approval_expiry_is_bounded() {
+    let now = 1_000;
+    assert!(TransferApproval::validate_expiry(0, now).is_err());
+    assert!(TransferApproval::validate_expiry(now, now).is_err());
+    assert!(TransferApproval::validate_expiry(now + 1, now).is_ok());
+    assert!(TransferApproval::validate_expiry(now + TransferApproval::MAX_TTL, now).is_ok());
+    assert!(TransferApproval::validate_expiry(now + TransferApproval::MAX_TTL + 1, now).is_err());
+    let mut approval = TransferApproval { remaining_amount: 1, expires_at: 0, ..Default::default() };
+    assert!(approval.draw(1, now).is_err());
+}
@@ -0,0 +1,47 @@
+use anchor_lang::AnchorSerialize;
+
+use crate::{ iso3166, state::UserKYC };
+
+#[test]
+fn test_country_table() {
+    assert!(iso3166::is_country(b"US"));
+    assert!(iso3166::is_country(b"AX"));
+    assert!(!iso3166::is_country(b"XX"));
+    assert!(!iso3166::is_country(b"us"));
+    assert!(UserKYC::validate_country("DE").is_ok());
+    assert!(UserKYC::validate_country("DEU").is_err());
+}
+
+#[test]
+fn test_subdivisions() {
+    assert!(iso3166::is_subdivision(b"US", "CA"));
+    assert!(!iso3166::is_subdivision(b"US", "ZZ"));
+    assert!(iso3166::is_subdivision(b"AU", "NSW"));
+    assert!(iso3166::is_subdivision(b"FR", "971"));
+    assert!(iso3166::is_subdivision(b"FR", "2A"));
+    assert!(!iso3166::is_subdivision(b"FR", "ZZZ"));
+    assert!(!iso3166::is_subdivision(b"XX", "CA"));
+    // no subdivisions assigned
+    assert!(!iso3166::is_subdivision(b"AX", "01"));
+    assert!(UserKYC::validate_subdivision("FR", "").is_ok());
+    assert!(UserKYC::validate_subdivision("CA", "CA").is_err());
+}
+
+#[test]
+fn test_three_character_subdivision_round_trips() {
+    let mut user_kyc = UserKYC::default();
+    user_kyc.set_state("971");
+    assert_eq!(user_kyc.get_state_str(), "971");
+    assert_eq!(user_kyc.state, [0; 2]);
+    assert_eq!(user_kyc.try_to_vec().unwrap().len(), UserKYC::LEN);
+}
+
+#[test]
+fn test_migration_moves_legacy_state() {
+    let mut user_kyc = UserKYC { state: *b"BY", ..Default::default() };
+    user_kyc.migrate_subdivision();
+    assert_eq!(user_kyc.get_state_str(), "BY");
+    assert_eq!(user_kyc.state, [0; 2]);
+    user_kyc.migrate_subdivision();
+    assert_eq!(user_kyc.get_state_str(), "BY");
+}
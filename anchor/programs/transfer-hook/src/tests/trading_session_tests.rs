@@ -0,0 +1,50 @@
+use crate::state::{ ComplianceModule, HolidayCalendar, SessionWindow };
+
+const DAY: i64 = 86400;
+// Monday 2024-01-01 00:00 UTC
+const MONDAY: i64 = 1_704_067_200;
+
+fn weekdays_9_to_5() -> Vec<SessionWindow> {
+    vec![SessionWindow { days: 0b0011111, open_minute: 9 * 60, close_minute: 17 * 60 }]
+}
+
+#[test]
+fn test_weekly_windows() {
+    let windows = weekdays_9_to_5();
+    assert!(SessionWindow::in_session(&windows, 0, None, MONDAY + 9 * 3600));
+    assert!(!SessionWindow::in_session(&windows, 0, None, MONDAY + 17 * 3600));
+    assert!(!SessionWindow::in_session(&windows, 0, None, MONDAY + 8 * 3600 + 3599));
+    assert!(SessionWindow::in_session(&windows, 0, None, MONDAY + 4 * DAY + 12 * 3600));
+    assert!(!SessionWindow::in_session(&windows, 0, None, MONDAY + 5 * DAY + 12 * 3600));
+}
+
+#[test]
+fn test_utc_offset_shifts_local_time() {
+    let windows = weekdays_9_to_5();
+    // 14:00 UTC is 09:00 in UTC-5
+    assert!(SessionWindow::in_session(&windows, -300, None, MONDAY + 14 * 3600));
+    assert!(!SessionWindow::in_session(&windows, -300, None, MONDAY + 13 * 3600));
+    // Monday 02:00 UTC is still Sunday in UTC-5
+    let all_day_monday = vec![SessionWindow { days: 0b1, open_minute: 0, close_minute: 1440 }];
+    assert!(!SessionWindow::in_session(&all_day_monday, -300, None, MONDAY + 2 * 3600));
+}
+
+#[test]
+fn test_holidays_close_the_session() {
+    let calendar = HolidayCalendar { days: vec![MONDAY / DAY], ..Default::default() };
+    let windows = weekdays_9_to_5();
+    assert!(!SessionWindow::in_session(&windows, 0, Some(&calendar), MONDAY + 10 * 3600));
+    assert!(SessionWindow::in_session(&windows, 0, Some(&calendar), MONDAY + DAY + 10 * 3600));
+}
+
+#[test]
+fn test_validate_sessions() {
+    let module = |utc_offset_minutes, windows| ComplianceModule::TradingSessions { utc_offset_minutes, windows };
+    assert!(module(60, weekdays_9_to_5()).validate().is_ok());
+    assert!(module(60, vec![]).validate().is_err());
+    assert!(module(15 * 60, weekdays_9_to_5()).validate().is_err());
+    let inverted = SessionWindow { days: 1, open_minute: 600, close_minute: 540 };
+    assert!(module(0, vec![inverted]).validate().is_err());
+    let no_days = SessionWindow { days: 0, open_minute: 0, close_minute: 1440 };
+    assert!(module(0, vec![no_days]).validate().is_err());
+}
@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::{
+    spl_token_2022::{
+        extension::{ transfer_hook::TransferHookAccount, BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut },
+        state::{ Account as TokenAccountState, AccountState },
+    },
+    ID as TOKEN_2022_ID,
+};
+
+use crate::instructions::is_transferring;
+
+/// Token-2022 account data for a mint with a transfer hook, `transferring` as Token-2022 would leave it
+fn token_account_data(transferring: bool) -> Vec<u8> {
+    let len = ExtensionType::try_calculate_account_len::<TokenAccountState>(&[ExtensionType::TransferHookAccount]).unwrap();
+    let mut data = vec![0; len];
+    let mut account = StateWithExtensionsMut::<TokenAccountState>::unpack_uninitialized(&mut data).unwrap();
+    account.base = TokenAccountState {
+        mint: Pubkey::new_unique(),
+        owner: Pubkey::new_unique(),
+        amount: 100,
+        state: AccountState::Initialized,
+        ..Default::default()
+    };
+    account.pack_base();
+    account.init_account_type().unwrap();
+    account.init_extension::<TransferHookAccount>(true).unwrap().transferring = transferring.into();
+    data
+}
+
+fn check(mut data: Vec<u8>) -> Result<bool> {
+    let key = Pubkey::new_unique();
+    let mut lamports = 0;
+    let ai = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &TOKEN_2022_ID, false, 0);
+    is_transferring(&ai)
+}
+
+#[test]
+fn test_hook_runs_inside_token_2022_transfer() {
+    assert!(check(token_account_data(true)).unwrap());
+}
+
+#[test]
+fn test_direct_hook_call_is_not_transferring() {
+    // outside a transfer Token-2022 has cleared the flag again
+    assert!(!check(token_account_data(false)).unwrap());
+}
+
+#[test]
+fn test_account_without_hook_extension_is_not_transferring() {
+    let mut data = vec![0; ExtensionType::try_calculate_account_len::<TokenAccountState>(&[]).unwrap()];
+    let mut account = StateWithExtensionsMut::<TokenAccountState>::unpack_uninitialized(&mut data).unwrap();
+    account.base = TokenAccountState { state: AccountState::Initialized, ..Default::default() };
+    account.pack_base();
+    assert!(!check(data).unwrap());
+}
@@ -0,0 +1,54 @@
+use anchor_lang::prelude::Pubkey;
+
+use crate::state::{ JournalEntry, TransferJournal };
+
+fn entry(slot: u64) -> JournalEntry {
+    JournalEntry { slot, amount: slot * 10, source_owner: Pubkey::new_unique(), ..Default::default() }
+}
+
+fn journal() -> TransferJournal {
+    bytemuck::Zeroable::zeroed()
+}
+
+#[test]
+fn test_full_ring_rejects_entries_until_archived() {
+    let mut journal = journal();
+    for slot in 0..TransferJournal::CAPACITY as u64 {
+        journal.append(entry(slot)).unwrap();
+    }
+    assert!(journal.is_full());
+    assert!(journal.append(entry(999)).is_err());
+    assert_eq!(journal.head, TransferJournal::CAPACITY as u64);
+
+    assert_eq!(journal.archive_page().unwrap()[0].sequence, 0);
+    journal.append(entry(999)).unwrap();
+    assert_eq!(journal.entries[0].sequence, TransferJournal::CAPACITY as u64);
+    assert_eq!(journal.entries[0].slot, 999);
+}
+
+#[test]
+fn test_archive_frees_oldest_page() {
+    let page_len = TransferJournal::PAGE_LEN as u64;
+    let mut journal = journal();
+    for slot in 0..page_len - 1 {
+        journal.append(entry(slot)).unwrap();
+    }
+    assert!(journal.archive_page().is_err());
+    for slot in page_len - 1..2 * page_len {
+        journal.append(entry(slot)).unwrap();
+    }
+
+    let page = journal.archive_page().unwrap();
+    assert_eq!(page[0].slot, 0);
+    assert_eq!(page[TransferJournal::PAGE_LEN - 1].slot, page_len - 1);
+    assert_eq!(journal.next_page(), 1);
+    assert!(!journal.is_full());
+
+    // the freed page is reused and archived in order
+    for slot in 2 * page_len..3 * page_len {
+        journal.append(entry(slot)).unwrap();
+    }
+    assert_eq!(journal.archive_page().unwrap()[0].slot, page_len);
+    assert_eq!(journal.archive_page().unwrap()[0].slot, 2 * page_len);
+    assert!(journal.archive_page().is_err());
+}
@@ -0,0 +1,28 @@
+use crate::state::TravelRuleRecord;
+
+#[test]
+fn test_record_is_consumed_once() {
+    let mut record = TravelRuleRecord { nonce: 1, amount: 5_000, ..Default::default() };
+    assert!(record.consume(5_000).is_ok());
+    assert!(record.consumed);
+    assert!(record.consume(5_000).is_err());
+}
+
+#[test]
+fn test_record_must_match_amount() {
+    let mut record = TravelRuleRecord { nonce: 1, amount: 5_000, ..Default::default() };
+    assert!(record.consume(4_999).is_err());
+    assert!(record.consume(5_001).is_err());
+    assert!(!record.consumed);
+}
+
+#[test]
+fn test_pending_record_is_not_replaced() {
+    let fresh = TravelRuleRecord::default();
+    assert!(fresh.check_replaceable(1).is_ok());
+    let mut record = TravelRuleRecord { nonce: 1, amount: 5_000, ..Default::default() };
+    assert!(record.check_replaceable(2).is_err());
+    assert!(record.consume(5_000).is_ok());
+    assert!(record.check_replaceable(1).is_err());
+    assert!(record.check_replaceable(2).is_ok());
+}
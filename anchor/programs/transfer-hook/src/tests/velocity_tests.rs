@@ -0,0 +1,64 @@
+use crate::state::{ ComplianceModule, HolderPosition, SlidingCount, VelocityLimits };
+
+const DAY: i64 = 86400;
+
+#[test]
+fn test_counts_slide_across_the_day_boundary() {
+    let mut position = HolderPosition::default();
+    position.record_velocity(DAY - 10, true);
+    position.record_velocity(DAY - 5, false);
+    assert_eq!(position.velocity_counts(DAY - 1), [2, 1, 1]);
+
+    // just after midnight the previous day's transfers still count in full
+    position.record_velocity(DAY + 10, true);
+    assert_eq!(position.velocity_counts(DAY + 10), [3, 2, 2]);
+    // and fade out over the following day
+    assert_eq!(position.velocity_counts(DAY + DAY / 2), [2, 2, 2]);
+    assert_eq!(position.velocity_counts(2 * DAY - 1), [2, 2, 2]);
+    assert_eq!(position.velocity_counts(3 * DAY), [0, 0, 2]);
+    assert_eq!(position.velocity_counts(90 * DAY), [0, 0, 0]);
+}
+
+#[test]
+fn test_sliding_count_rounds_up() {
+    let mut count = SlidingCount::default();
+    for _ in 0..3 {
+        count.add(DAY - 1, DAY);
+    }
+    assert_eq!(count.count(DAY, DAY), 3);
+    assert_eq!(count.count(DAY + DAY / 2, DAY), 2);
+    assert_eq!(count.count(2 * DAY - 1, DAY), 1);
+    assert_eq!(count.count(2 * DAY, DAY), 0);
+}
+
+#[test]
+fn test_limits_reached_and_exceeded() {
+    let limits = VelocityLimits { daily_transfers: 0, daily_near_threshold: 2, monthly_near_threshold: 0 };
+    let mut position = HolderPosition::default();
+    position.record_velocity(0, true);
+    assert!(!limits.reached_by(&position, 0));
+    position.record_velocity(0, true);
+    assert!(limits.reached_by(&position, 0));
+    assert!(!limits.exceeded_by(&position, 0));
+    position.record_velocity(0, true);
+    assert!(limits.exceeded_by(&position, 0));
+
+    let unlimited = VelocityLimits::default();
+    assert!(!unlimited.reached_by(&position, 0) && !unlimited.exceeded_by(&position, 0));
+}
+
+#[test]
+fn test_validate_velocity() {
+    let module = |reporting_threshold, near_threshold_bps, risk_increase| ComplianceModule::Velocity {
+        reporting_threshold,
+        near_threshold_bps,
+        review: VelocityLimits::default(),
+        block: VelocityLimits::default(),
+        risk_increase,
+    };
+    assert!(module(10_000, 9_000, 20).validate().is_ok());
+    assert!(module(0, 9_000, 20).validate().is_err());
+    assert!(module(10_000, 0, 20).validate().is_err());
+    assert!(module(10_000, 10_001, 20).validate().is_err());
+    assert!(module(10_000, 9_000, 101).validate().is_err());
+}
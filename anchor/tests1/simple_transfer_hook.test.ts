@@ -504,15 +504,15 @@ describe('Simple Transfer Hook Test', () => {
     // 3. Update KYC - upgrade level and add flags
     console.log('🔄 Updating KYC...')
     await program2.methods
-      .updateUserKyc(
-        3, // Institutional level
-        25, // Lower risk score
-        0x02, // Set PEP flag
-        null, // No flags to clear
-        null, // Don't change country
-        null, // Don't change state
-        'Los Angeles', // Change city
-      )
+      .updateUserKyc({
+        kycLevel: 3, // Institutional level
+        riskScore: 25, // Lower risk score
+        flagsToSet: 0x02, // Set PEP flag
+        flagsToClear: null, // No flags to clear
+        country: null, // Don't change country
+        state: null, // Don't change state
+        city: 'Los Angeles', // Change city
+      })
       .accountsPartial({
         authority: payer.publicKey,
         user: testUser.publicKey,
@@ -544,15 +544,15 @@ describe('Simple Transfer Hook Test', () => {
     // 5. Test flag clearing
     console.log('🚩 Testing flag clearing...')
     await program2.methods
-      .updateUserKyc(
-        null, // Don't change level
-        null, // Don't change risk score
-        null, // No flags to set
-        0x02, // Clear PEP flag
-        null, // Don't change country
-        null, // Don't change state
-        null, // Don't change city
-      )
+      .updateUserKyc({
+        kycLevel: null, // Don't change level
+        riskScore: null, // Don't change risk score
+        flagsToSet: null, // No flags to set
+        flagsToClear: 0x02, // Clear PEP flag
+        country: null, // Don't change country
+        state: null, // Don't change state
+        city: null, // Don't change city
+      })
       .accountsPartial({
         authority: payer.publicKey,
         user: testUser.publicKey,
@@ -653,15 +653,15 @@ describe('Simple Transfer Hook Test', () => {
     // TEST 1: Country restriction (change user to France - not in allowlist)
     console.log('\n🌍 TEST 1: Country Restriction Failure')
     await program2.methods
-      .updateUserKyc(
-        null, // Don't change level
-        null, // Don't change risk score
-        null, // No flags to set
-        null, // No flags to clear
-        'FR', // Change to France (not in US,CA,GB allowlist)
-        null, // Don't change state
-        null, // Don't change city
-      )
+      .updateUserKyc({
+        kycLevel: null, // Don't change level
+        riskScore: null, // Don't change risk score
+        flagsToSet: null, // No flags to set
+        flagsToClear: null, // No flags to clear
+        country: 'FR', // Change to France (not in US,CA,GB allowlist)
+        state: null, // Don't change state
+        city: null, // Don't change city
+      })
       .accountsPartial({
         authority: payer.publicKey,
         user: user1.publicKey,
@@ -675,15 +675,15 @@ describe('Simple Transfer Hook Test', () => {
     // TEST 2: State restriction (change to US_NY - restricted state)
     console.log('\n🏛️ TEST 2: State Restriction Failure')
     await program2.methods
-      .updateUserKyc(
-        null, // Don't change level
-        null, // Don't change risk score
-        null, // No flags to set
-        null, // No flags to clear
-        'US', // Back to US
-        'NY', // Change to NY (restricted state)
-        null, // Don't change city
-      )
+      .updateUserKyc({
+        kycLevel: null, // Don't change level
+        riskScore: null, // Don't change risk score
+        flagsToSet: null, // No flags to set
+        flagsToClear: null, // No flags to clear
+        country: 'US', // Back to US
+        state: 'NY', // Change to NY (restricted state)
+        city: null, // Don't change city
+      })
       .accountsPartial({
         authority: payer.publicKey,
         user: user1.publicKey,
@@ -697,15 +697,15 @@ describe('Simple Transfer Hook Test', () => {
     // TEST 3: Sanctions flag
     console.log('\n🛑 TEST 3: Sanctions Flag Failure')
     await program2.methods
-      .updateUserKyc(
-        null, // Don't change level
-        null, // Don't change risk score
-        0x01, // Set sanctions flag (UserKYC::FLAG_SANCTIONS)
-        null, // No flags to clear
-        'US', // Back to allowed country
-        'CA', // Back to allowed state
-        null, // Don't change city
-      )
+      .updateUserKyc({
+        kycLevel: null, // Don't change level
+        riskScore: null, // Don't change risk score
+        flagsToSet: 0x01, // Set sanctions flag (UserKYC::FLAG_SANCTIONS)
+        flagsToClear: null, // No flags to clear
+        country: 'US', // Back to allowed country
+        state: 'CA', // Back to allowed state
+        city: null, // Don't change city
+      })
       .accountsPartial({
         authority: payer.publicKey,
         user: user1.publicKey,
@@ -719,15 +719,15 @@ describe('Simple Transfer Hook Test', () => {
     // TEST 4: Frozen account flag
     console.log('\n🧊 TEST 4: Frozen Account Flag Failure')
     await program2.methods
-      .updateUserKyc(
-        null, // Don't change level
-        null, // Don't change risk score
-        0x04, // Set frozen flag (UserKYC::FLAG_FROZEN)
-        0x01, // Clear sanctions flag
-        null, // Don't change country
-        null, // Don't change state
-        null, // Don't change city
-      )
+      .updateUserKyc({
+        kycLevel: null, // Don't change level
+        riskScore: null, // Don't change risk score
+        flagsToSet: 0x04, // Set frozen flag (UserKYC::FLAG_FROZEN)
+        flagsToClear: 0x01, // Clear sanctions flag
+        country: null, // Don't change country
+        state: null, // Don't change state
+        city: null, // Don't change city
+      })
       .accountsPartial({
         authority: payer.publicKey,
         user: user1.publicKey,
@@ -741,15 +741,15 @@ describe('Simple Transfer Hook Test', () => {
     // TEST 5: Insufficient KYC level
     console.log('\n📋 TEST 5: Insufficient KYC Level Failure')
     await program2.methods
-      .updateUserKyc(
-        0, // Set to Unverified level
-        null, // Don't change risk score
-        null, // No flags to set
-        0x04, // Clear frozen flag
-        null, // Don't change country
-        null, // Don't change state
-        null, // Don't change city
-      )
+      .updateUserKyc({
+        kycLevel: 0, // Set to Unverified level
+        riskScore: null, // Don't change risk score
+        flagsToSet: null, // No flags to set
+        flagsToClear: 0x04, // Clear frozen flag
+        country: null, // Don't change country
+        state: null, // Don't change state
+        city: null, // Don't change city
+      })
       .accountsPartial({
         authority: payer.publicKey,
         user: user1.publicKey,
@@ -763,15 +763,15 @@ describe('Simple Transfer Hook Test', () => {
     // CLEANUP: Restore user1 to compliant state for other tests
     console.log('\n🔄 CLEANUP: Restoring user1 to compliant state')
     await program2.methods
-      .updateUserKyc(
-        2, // Enhanced level
-        null, // Don't change risk score
-        null, // No flags to set
-        null, // No flags to clear
-        'US', // Allowed country
-        'CA', // Allowed state
-        'San Francisco', // Restore city
-      )
+      .updateUserKyc({
+        kycLevel: 2, // Enhanced level
+        riskScore: null, // Don't change risk score
+        flagsToSet: null, // No flags to set
+        flagsToClear: null, // No flags to clear
+        country: 'US', // Allowed country
+        state: 'CA', // Allowed state
+        city: 'San Francisco', // Restore city
+      })
       .accountsPartial({
         authority: payer.publicKey,
         user: user1.publicKey,
@@ -1394,15 +1394,15 @@ export class RwaAmmSdk {
       )[0]
 
       const transaction = await this.transferHookProgram.methods
-        .updateUserKyc(
-          params.newKycLevel ?? null,
-          params.newRiskScore ?? null,
-          params.flagsToSet ?? null,
-          params.flagsToClear ?? null,
-          params.newCountry ?? null,
-          params.newState ?? null,
-          params.newCity ?? null,
-        )
+        .updateUserKyc({
+          kycLevel: params.newKycLevel ?? null,
+          riskScore: params.newRiskScore ?? null,
+          flagsToSet: params.flagsToSet ?? null,
+          flagsToClear: params.flagsToClear ?? null,
+          country: params.newCountry ?? null,
+          state: params.newState ?? null,
+          city: params.newCity ?? null,
+        })
         .accountsPartial({
           authority: payer.publicKey,
           user: params.userPublicKey,